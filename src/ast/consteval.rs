@@ -0,0 +1,122 @@
+use crate::ast::{Ast, LiteralKind};
+use crate::tokenizer::tokentype::TokenType;
+use crate::types::Type;
+
+impl Ast {
+    /// Evaluates an integer constant expression, `None` when the value is
+    /// only known at run time.
+    pub fn eval_const(&self) -> Option<i64> {
+        match self {
+            Ast::Literal(lit) => lit.val.parse().ok(),
+            Ast::Unary(u) => {
+                let val = u.right.eval_const()?;
+                let val = match u.token.kind {
+                    TokenType::TokenMinus => val.wrapping_neg(),
+                    TokenType::TokenPlus => val,
+                    TokenType::TokenTilde => !val,
+                    TokenType::TokenBang => (val == 0) as i64,
+                    _ => return None,
+                };
+                match &u.ty {
+                    Some(ty) if ty.is_integer() => Some(ty.convert(val)),
+                    _ => Some(val),
+                }
+            }
+            Ast::Cast(c) => Some(c.ty.convert(c.expr.eval_const()?)),
+            Ast::Ternary(t) => match t.cond.eval_const()? {
+                0 => t.alt.eval_const(),
+                _ => t.then.eval_const(),
+            },
+            Ast::ArithExpr(arith) => {
+                let left = arith.left.eval_const()?;
+                // the right operand does not have to be constant when it is
+                // not evaluated
+                match arith.token.kind {
+                    TokenType::TokenAndAnd if left == 0 => return Some(0),
+                    TokenType::TokenPipePipe if left != 0 => return Some(1),
+                    _ => {}
+                }
+                let right = arith.right.eval_const()?;
+                // operands are converted to their common type, the left one
+                // tells if it is unsigned
+                let unsigned = arith.left.ty().is_some_and(|ty| ty.is_unsigned());
+                let (l, r) = (left as u64, right as u64);
+                let val = match arith.token.kind {
+                    TokenType::TokenSlash if unsigned => l.checked_div(r).map(|v| v as i64),
+                    TokenType::TokenPercent if unsigned => l.checked_rem(r).map(|v| v as i64),
+                    TokenType::TokenGreaterGreater if unsigned => {
+                        Some(l.wrapping_shr(right as u32) as i64)
+                    }
+                    TokenType::TokenLess if unsigned => Some((l < r) as i64),
+                    TokenType::TokenLessEqual if unsigned => Some((l <= r) as i64),
+                    TokenType::TokenGreater if unsigned => Some((l > r) as i64),
+                    TokenType::TokenGreaterEqual if unsigned => Some((l >= r) as i64),
+                    TokenType::TokenPlus => Some(left.wrapping_add(right)),
+                    TokenType::TokenMinus => Some(left.wrapping_sub(right)),
+                    TokenType::TokenStar => Some(left.wrapping_mul(right)),
+                    TokenType::TokenSlash => left.checked_div(right),
+                    TokenType::TokenPercent => left.checked_rem(right),
+                    TokenType::TokenAddr => Some(left & right),
+                    TokenType::TokenCaret => Some(left ^ right),
+                    TokenType::TokenPipe => Some(left | right),
+                    TokenType::TokenLessLess => Some(left.wrapping_shl(right as u32)),
+                    TokenType::TokenGreaterGreater => Some(left.wrapping_shr(right as u32)),
+                    TokenType::TokenAndAnd | TokenType::TokenPipePipe => Some((right != 0) as i64),
+                    TokenType::TokenEqualEqual => Some((left == right) as i64),
+                    TokenType::TokenBangEqual => Some((left != right) as i64),
+                    TokenType::TokenLess => Some((left < right) as i64),
+                    TokenType::TokenLessEqual => Some((left <= right) as i64),
+                    TokenType::TokenGreater => Some((left > right) as i64),
+                    TokenType::TokenGreaterEqual => Some((left >= right) as i64),
+                    _ => None,
+                }?;
+                match &arith.ty {
+                    Some(ty) if ty.is_integer() => Some(ty.convert(val)),
+                    _ => Some(val),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Evaluates an address constant, the address of a global plus a
+    /// constant offset in bytes. Used by pointer initializers of globals.
+    pub fn eval_address(&self) -> Option<(String, i64)> {
+        match self {
+            Ast::Literal(lit) if matches!(lit.kind, LiteralKind::String(_)) => {
+                Some((lit.val.clone(), 0))
+            }
+            // arrays and functions decay to their address
+            Ast::Variable(v) if v.is_global => match v.ty {
+                Some(Type::Array(_, _)) | Some(Type::Function(_, _)) => Some((v.val.clone(), 0)),
+                _ => None,
+            },
+            Ast::Unary(u) if u.token.is(TokenType::TokenAddr) => u.right.eval_lvalue_address(),
+            Ast::Cast(c) if c.ty.size() == 8 => c.expr.eval_address(),
+            Ast::ArithExpr(arith) => {
+                let (label, offset) = arith.left.eval_address()?;
+                let scale = arith.left.ty()?.base()?.size() as i64;
+                let delta = arith.right.eval_const()?.wrapping_mul(scale);
+                match arith.token.kind {
+                    TokenType::TokenPlus => Some((label, offset + delta)),
+                    TokenType::TokenMinus => Some((label, offset - delta)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The address constant of an lvalue, for `&lvalue`.
+    fn eval_lvalue_address(&self) -> Option<(String, i64)> {
+        match self {
+            Ast::Variable(v) if v.is_global => Some((v.val.clone(), 0)),
+            Ast::Unary(u) if u.token.is(TokenType::TokenDeref) => u.right.eval_address(),
+            Ast::MemberAccess(m) => {
+                let (label, offset) = m.left.eval_lvalue_address()?;
+                Some((label, offset + m.offset as i64))
+            }
+            _ => None,
+        }
+    }
+}
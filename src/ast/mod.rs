@@ -1,4 +1,8 @@
+use crate::parser::localscope::LocalScope;
 use crate::tokenizer::token::Token;
+use crate::types::Type;
+
+mod consteval;
 
 #[derive(Clone, Debug)]
 pub enum StatementsKind {
@@ -16,8 +20,8 @@ pub struct Statements {
 pub enum LiteralKind {
     Integer(),
     // Float(u64, FloatKind),
-    String(String),
-    Char(char),
+    String(Vec<u8>),
+    Char,
     Bool(bool),
     Unit,
 }
@@ -26,14 +30,30 @@ pub enum LiteralKind {
 pub struct Literal {
     pub kind: LiteralKind,
     pub val: String,
+    pub ty: Option<Type>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Variable {
-    pub id: i8,
+    pub id: usize,
     pub token: Token,
     pub val: String,
-    pub assign: Option<Box<Ast>>,
+    pub is_global: bool,
+    pub ty: Option<Type>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Assign {
+    pub token: Token,
+    pub left: Box<Ast>,
+    pub right: Box<Ast>,
+    pub ty: Option<Type>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub token: Token,
+    pub inits: Vec<Ast>,
 }
 
 #[derive(Debug, Clone)]
@@ -41,6 +61,7 @@ pub struct Unary {
     pub token: Token,
     pub operator: String,
     pub right: Box<Ast>,
+    pub ty: Option<Type>,
 }
 
 #[derive(Debug, Clone)]
@@ -49,10 +70,37 @@ pub struct ArithExpr {
     pub operator: String,
     pub left: Box<Ast>,
     pub right: Box<Ast>,
+    pub ty: Option<Type>,
 }
 
+/// `cond ? then : alt`, only one of the operands is evaluated.
 #[derive(Debug, Clone)]
-pub struct CallExpr {}
+pub struct Ternary {
+    pub token: Token,
+    pub cond: Box<Ast>,
+    pub then: Box<Ast>,
+    pub alt: Box<Ast>,
+    pub ty: Option<Type>,
+}
+
+/// A conversion of `expr` to `ty`, either written as `(type)expr` or
+/// inserted by the type checker for the usual arithmetic conversions.
+#[derive(Debug, Clone)]
+pub struct Cast {
+    pub token: Token,
+    pub expr: Box<Ast>,
+    pub ty: Type,
+}
+
+/// `left.name`, the offset of the member is filled in by the type checker.
+#[derive(Debug, Clone)]
+pub struct MemberAccess {
+    pub token: Token,
+    pub left: Box<Ast>,
+    pub name: String,
+    pub offset: i32,
+    pub ty: Option<Type>,
+}
 
 #[derive(Debug, Clone)]
 pub struct If {
@@ -62,6 +110,33 @@ pub struct If {
     pub alt: Option<Box<Ast>>,
 }
 
+/// A `case` label of a switch, `default` when it has no expression. The
+/// value of the expression is filled in by the type checker.
+#[derive(Debug, Clone)]
+pub struct CaseLabel {
+    pub token: Token,
+    pub expr: Option<Box<Ast>>,
+    pub value: i64,
+}
+
+/// `switch (cond) body`, `cases` being the labels of the body that belong
+/// to this switch and not to a nested one, in source order.
+#[derive(Debug, Clone)]
+pub struct Switch {
+    pub token: Token,
+    pub cond: Box<Ast>,
+    pub body: Box<Ast>,
+    pub cases: Vec<CaseLabel>,
+}
+
+/// The statement of the body of a switch at the label `index` of its
+/// cases.
+#[derive(Debug, Clone)]
+pub struct Case {
+    pub index: usize,
+    pub stmt: Box<Ast>,
+}
+
 #[derive(Clone, Debug)]
 pub enum LoopKind {
     For,
@@ -79,8 +154,64 @@ pub struct Loop {
     pub then: Box<Ast>,
 }
 
+#[derive(Clone, Debug)]
+pub enum JumpKind {
+    Break,
+    Continue,
+    Goto(String),
+}
+
+/// `break` and `continue` jump out of or to the next iteration of the
+/// innermost loop, `goto` to a label of the function.
+#[derive(Debug, Clone)]
+pub struct Jump {
+    pub kind: JumpKind,
+    pub token: Token,
+}
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub name: String,
+    pub stmt: Box<Ast>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionCall {
+    pub token: Token,
+    pub name: String,
+    pub args: Vec<Ast>,
+    pub ty: Option<Type>,
+}
+
+/// A constant value of a global initializer, either a plain value or the
+/// address of another symbol plus an offset in bytes.
+#[derive(Debug, Clone)]
+pub enum GlobalInit {
+    Value(i64),
+    Address(String, i64),
+}
+
+#[derive(Debug, Clone)]
+pub struct GlobalVar {
+    pub token: Token,
+    pub name: String,
+    pub ty: Type,
+    pub is_static: bool,
+    pub read_only: bool,
+    pub init: Vec<Ast>,
+    pub data: Vec<GlobalInit>,
+}
+
 #[derive(Debug, Clone)]
-pub struct FunctionCall {}
+pub struct Function {
+    pub token: Token,
+    pub name: String,
+    pub ty: Type,
+    pub is_static: bool,
+    pub params: Vec<usize>,
+    pub body: Box<Ast>,
+    pub scope: LocalScope,
+}
 
 #[derive(Debug, Clone)]
 pub struct Return {
@@ -93,15 +224,42 @@ pub enum Ast {
     Statements(Statements),
     Literal(Literal),
     Variable(Variable),
+    Assign(Assign),
+    Declaration(Declaration),
     Unary(Unary),
     ArithExpr(ArithExpr),
+    Ternary(Ternary),
+    Cast(Cast),
+    MemberAccess(MemberAccess),
     If(If),
+    Switch(Switch),
+    Case(Case),
     Loop(Loop),
+    Jump(Jump),
+    Label(Label),
     FunctionCall(FunctionCall),
+    Function(Function),
+    GlobalVar(GlobalVar),
     Return(Return),
 }
 
 impl Ast {
+    /// The type attached by the type checker, `None` for statements.
+    pub fn ty(&self) -> Option<&Type> {
+        match self {
+            Ast::Literal(lit) => lit.ty.as_ref(),
+            Ast::Variable(v) => v.ty.as_ref(),
+            Ast::Assign(a) => a.ty.as_ref(),
+            Ast::Unary(u) => u.ty.as_ref(),
+            Ast::ArithExpr(arith) => arith.ty.as_ref(),
+            Ast::Ternary(t) => t.ty.as_ref(),
+            Ast::Cast(c) => Some(&c.ty),
+            Ast::MemberAccess(m) => m.ty.as_ref(),
+            Ast::FunctionCall(f) => f.ty.as_ref(),
+            _ => None,
+        }
+    }
+
     pub fn new_program(asts: Vec<Ast>) -> Ast {
         Ast::Statements(Statements {
             kind: StatementsKind::Program,
@@ -120,33 +278,94 @@ impl Ast {
         Ast::Literal(Literal {
             kind: LiteralKind::Integer(),
             val,
+            ty: None,
+        })
+    }
+
+    /// A string literal, `label` names its contents emitted in .rodata.
+    pub fn new_string_literal(label: String, bytes: Vec<u8>) -> Ast {
+        Ast::Literal(Literal {
+            kind: LiteralKind::String(bytes),
+            val: label,
+            ty: None,
+        })
+    }
+
+    /// A character constant, `value` is the decoded character.
+    pub fn new_char_literal(value: i64) -> Ast {
+        Ast::Literal(Literal {
+            kind: LiteralKind::Char,
+            val: value.to_string(),
+            ty: Some(Type::Int),
+        })
+    }
+
+    pub fn new_typed_literal(val: String, ty: Type) -> Ast {
+        Ast::Literal(Literal {
+            kind: LiteralKind::Integer(),
+            val,
+            ty: Some(ty),
         })
     }
 
-    pub fn new_variable(id: i8, token: Token, val: String) -> Ast {
+    pub fn new_variable(id: usize, token: Token, val: String) -> Ast {
         Ast::Variable(Variable {
             id,
             token,
             val,
-            assign: None,
+            is_global: false,
+            ty: None,
         })
     }
 
-    pub fn new_variable_assign(id: i8, token: Token, val: String, ast: Ast) -> Ast {
+    pub fn new_global_variable(token: Token, val: String) -> Ast {
         Ast::Variable(Variable {
-            id,
+            id: 0,
             token,
             val,
-            assign: Some(Box::new(ast)),
+            is_global: true,
+            ty: None,
+        })
+    }
+
+    pub fn new_assign(token: Token, left: Ast, right: Ast) -> Ast {
+        Ast::Assign(Assign {
+            token,
+            left: Box::new(left),
+            right: Box::new(right),
+            ty: None,
         })
     }
 
+    pub fn new_declaration(token: Token, inits: Vec<Ast>) -> Ast {
+        Ast::Declaration(Declaration { token, inits })
+    }
+
     pub fn new_arith_exp(token: Token, operator: String, left: Ast, right: Ast) -> Ast {
         Ast::ArithExpr(ArithExpr {
             token,
             operator,
             left: Box::new(left),
             right: Box::new(right),
+            ty: None,
+        })
+    }
+
+    pub fn new_ternary(token: Token, cond: Ast, then: Ast, alt: Ast) -> Ast {
+        Ast::Ternary(Ternary {
+            token,
+            cond: Box::new(cond),
+            then: Box::new(then),
+            alt: Box::new(alt),
+            ty: None,
+        })
+    }
+
+    pub fn new_cast(token: Token, expr: Ast, ty: Type) -> Ast {
+        Ast::Cast(Cast {
+            token,
+            expr: Box::new(expr),
+            ty,
         })
     }
 
@@ -155,6 +374,17 @@ impl Ast {
             token,
             operator,
             right: Box::new(right),
+            ty: None,
+        })
+    }
+
+    pub fn new_member_access(token: Token, left: Ast, name: String) -> Ast {
+        Ast::MemberAccess(MemberAccess {
+            token,
+            left: Box::new(left),
+            name,
+            offset: 0,
+            ty: None,
         })
     }
 
@@ -184,12 +414,39 @@ impl Ast {
         })
     }
 
-    pub fn new_do_while(tok: Token, then: Ast) -> Ast {
+    pub fn new_switch(token: Token, cond: Ast, body: Ast, cases: Vec<CaseLabel>) -> Ast {
+        Ast::Switch(Switch {
+            token,
+            cond: Box::new(cond),
+            body: Box::new(body),
+            cases,
+        })
+    }
+
+    pub fn new_case(index: usize, stmt: Ast) -> Ast {
+        Ast::Case(Case {
+            index,
+            stmt: Box::new(stmt),
+        })
+    }
+
+    pub fn new_jump(token: Token, kind: JumpKind) -> Ast {
+        Ast::Jump(Jump { kind, token })
+    }
+
+    pub fn new_label(name: String, stmt: Ast) -> Ast {
+        Ast::Label(Label {
+            name,
+            stmt: Box::new(stmt),
+        })
+    }
+
+    pub fn new_do_while(tok: Token, cond: Ast, then: Ast) -> Ast {
         Ast::Loop(Loop {
-            kind: LoopKind::While,
+            kind: LoopKind::DoWhile,
             tok,
             init: None,
-            cond: None,
+            cond: Some(Box::new(cond)),
             incr: None,
             then: Box::new(then),
         })
@@ -235,7 +492,67 @@ impl Ast {
         })
     }
 
-    pub fn new_func_call() -> Ast {
-        Ast::FunctionCall(FunctionCall {})
+    pub fn new_func_call(token: Token, name: String, args: Vec<Ast>) -> Ast {
+        Ast::FunctionCall(FunctionCall {
+            token,
+            name,
+            args,
+            ty: None,
+        })
+    }
+
+    pub fn new_function(
+        token: Token,
+        name: String,
+        ty: Type,
+        is_static: bool,
+        params: Vec<usize>,
+        body: Ast,
+        scope: LocalScope,
+    ) -> Ast {
+        Ast::Function(Function {
+            token,
+            name,
+            ty,
+            is_static,
+            params,
+            body: Box::new(body),
+            scope,
+        })
+    }
+
+    pub fn new_global_var(
+        token: Token,
+        name: String,
+        ty: Type,
+        is_static: bool,
+        init: Vec<Ast>,
+    ) -> Ast {
+        Ast::GlobalVar(GlobalVar {
+            token,
+            name,
+            ty,
+            is_static,
+            read_only: false,
+            init,
+            data: Vec::new(),
+        })
+    }
+
+    /// The nul terminated contents of a string literal.
+    pub fn new_string_data(token: Token, label: String, bytes: &[u8]) -> Ast {
+        let mut data: Vec<GlobalInit> =
+            bytes.iter().map(|b| GlobalInit::Value(*b as i64)).collect();
+        data.push(GlobalInit::Value(0));
+
+        Ast::GlobalVar(GlobalVar {
+            token,
+            name: label,
+            ty: Type::array_of(Type::Char, data.len()),
+            is_static: true,
+            read_only: true,
+            init: Vec::new(),
+            data,
+        })
     }
 }
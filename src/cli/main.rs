@@ -1,15 +1,260 @@
 use std::env;
+use std::fs;
+use std::io;
+use std::io::{IsTerminal, Read};
+use std::path::Path;
+use std::process;
 
-pub static DEFAULT_FILENAME: &str = "tmp.s";
+use chibicc::diagnostic::{Diagnostic, Severity};
+use chibicc::{CompileOptions, MacroDef};
+
+static USAGE: &str = "usage: chibicc [-o <path>] [-I <dir>] [-D <name>[=<value>]] [-U <name>] \
+                     [-E [-dM]] [-fdiagnostics-color[=auto|always|never]] [-fmax-errors=<n>] \
+                     <file>...";
+
+/// Reads source files, `-` being the standard input.
+static STDIN: &str = "-";
+
+struct Options {
+    output: Option<String>,
+    inputs: Vec<String>,
+    // colored diagnostics, `None` when it depends on stderr being a terminal
+    color: Option<bool>,
+    // errors reported per file before giving up on it, 0 for no limit
+    max_errors: usize,
+    // `-E`, stop after preprocessing
+    preprocess_only: bool,
+    // `-dM` with `-E`, print the macros defined instead of the preprocessed
+    // source
+    dump_macros: bool,
+    compile: CompileOptions,
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let mut opts = match parse_args(&args) {
+        Ok(opts) => opts,
+        Err(msg) => {
+            eprintln!("chibicc: error: {}", msg);
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    opts.compile.source_date_epoch = match source_date_epoch() {
+        Ok(epoch) => epoch,
+        Err(msg) => {
+            eprintln!("chibicc: error: {}", msg);
+            process::exit(1);
+        }
+    };
+
+    let color = opts.color.unwrap_or_else(|| io::stderr().is_terminal());
+
+    let mut failed = false;
+    for input in opts.inputs.iter() {
+        let output = match &opts.output {
+            Some(output) => output.clone(),
+            None if opts.preprocess_only => STDIN.to_string(),
+            None => output_path(input),
+        };
+
+        if let Err(errors) = compile_file(input, &output, &opts) {
+            let limit = match opts.max_errors {
+                0 => errors.len(),
+                n => n,
+            };
+
+            for error in errors.iter().take(limit) {
+                eprintln!("{}", error.render(color));
+            }
+            if errors.len() > limit {
+                eprintln!("compilation terminated due to -fmax-errors={}.", limit);
+            }
+            failed = true;
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut opts = Options {
+        output: None,
+        inputs: Vec::new(),
+        color: None,
+        max_errors: 0,
+        preprocess_only: false,
+        dump_macros: false,
+        compile: CompileOptions::default(),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "-o" {
+            let output = args.next().ok_or("missing filename after '-o'")?;
+            opts.output = Some(output.clone());
+            continue;
+        }
+
+        if let Some(output) = arg.strip_prefix("-o") {
+            opts.output = Some(output.to_string());
+            continue;
+        }
+
+        if arg == "-I" {
+            let dir = args.next().ok_or("missing path after '-I'")?;
+            opts.compile.include_paths.push(dir.clone());
+            continue;
+        }
+
+        if let Some(dir) = arg.strip_prefix("-I") {
+            opts.compile.include_paths.push(dir.to_string());
+            continue;
+        }
+
+        if arg == "-E" {
+            opts.preprocess_only = true;
+            continue;
+        }
+
+        if arg == "-dM" {
+            opts.dump_macros = true;
+            continue;
+        }
+
+        if arg == "-D" {
+            let def = args.next().ok_or("missing macro name after '-D'")?;
+            opts.compile.macros.push(define(def));
+            continue;
+        }
+
+        if let Some(def) = arg.strip_prefix("-D") {
+            opts.compile.macros.push(define(def));
+            continue;
+        }
+
+        if arg == "-U" {
+            let name = args.next().ok_or("missing macro name after '-U'")?;
+            opts.compile.macros.push(MacroDef::Undef(name.clone()));
+            continue;
+        }
+
+        if let Some(name) = arg.strip_prefix("-U") {
+            opts.compile.macros.push(MacroDef::Undef(name.to_string()));
+            continue;
+        }
+
+        if let Some(when) = diagnostics_color(arg) {
+            opts.color = match when {
+                "always" => Some(true),
+                "never" => Some(false),
+                "auto" => None,
+                _ => {
+                    return Err(format!(
+                        "invalid argument '{}' to -fdiagnostics-color",
+                        when
+                    ))
+                }
+            };
+            continue;
+        }
 
-    if args.len() != 2 {
-        eprintln!("{}: invalid number of arguments", args.len());
-        return;
+        if let Some(n) = arg.strip_prefix("-fmax-errors=") {
+            opts.max_errors = n
+                .parse()
+                .map_err(|_| format!("invalid argument '{}' to -fmax-errors", n))?;
+            continue;
+        }
+
+        if arg.starts_with('-') && arg != STDIN {
+            return Err(format!("unknown argument '{}'", arg));
+        }
+
+        opts.inputs.push(arg.clone());
+    }
+
+    if opts.inputs.is_empty() {
+        return Err("no input files".to_string());
+    }
+
+    if opts.output.is_some() && opts.inputs.len() > 1 {
+        return Err("cannot specify '-o' with multiple files".to_string());
+    }
+
+    if opts.dump_macros && !opts.preprocess_only {
+        return Err("'-dM' is only supported with '-E'".to_string());
+    }
+
+    Ok(opts)
+}
+
+/// `SOURCE_DATE_EPOCH` from the environment, the time of `__DATE__` and
+/// `__TIME__` in seconds since 1970.
+fn source_date_epoch() -> Result<Option<u64>, String> {
+    let epoch = match env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch,
+        Err(_) => return Ok(None),
+    };
+
+    epoch.parse().map(Some).map_err(|_| {
+        "environment variable SOURCE_DATE_EPOCH must expand to a non-negative integer".to_string()
+    })
+}
+
+/// `-D name=value` defines `name` as `value`, `-D name` as `1`.
+fn define(def: &str) -> MacroDef {
+    match def.split_once('=') {
+        Some((name, body)) => MacroDef::Define(name.to_string(), body.to_string()),
+        None => MacroDef::Define(def.to_string(), "1".to_string()),
     }
+}
 
-    let input = args.get(1).unwrap();
-    chibicc::gen(input, DEFAULT_FILENAME);
+/// The value of `-fdiagnostics-color[=when]`, `-fno-diagnostics-color`
+/// being `never`.
+fn diagnostics_color(arg: &str) -> Option<&str> {
+    match arg {
+        "-fdiagnostics-color" => Some("always"),
+        "-fno-diagnostics-color" => Some("never"),
+        _ => arg.strip_prefix("-fdiagnostics-color="),
+    }
+}
+
+/// `foo/bar.c` is compiled to `bar.s` in the working directory, the
+/// standard input to the standard output.
+fn output_path(input: &str) -> String {
+    if input == STDIN {
+        return STDIN.to_string();
+    }
+
+    let stem = Path::new(input).file_stem().unwrap_or_default();
+    Path::new(stem)
+        .with_extension("s")
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn compile_file(input: &str, output: &str, opts: &Options) -> Result<(), Vec<Diagnostic>> {
+    let (path, source) = match input == STDIN {
+        true => {
+            let mut source = String::new();
+            let read = io::stdin().read_to_string(&mut source);
+            ("<stdin>", read.map(|_| source))
+        }
+        false => (input, fs::read_to_string(input)),
+    };
+
+    let source = source.map_err(|e| {
+        let msg = format!("cannot read {}: {}", path, e);
+        vec![Diagnostic::new(Severity::Error, msg)]
+    })?;
+
+    match (opts.preprocess_only, opts.dump_macros) {
+        (true, true) => chibicc::dump_macros(&source, path, output, &opts.compile),
+        (true, false) => chibicc::preprocess(&source, path, output, &opts.compile),
+        _ => chibicc::gen(&source, path, output, &opts.compile),
+    }
 }
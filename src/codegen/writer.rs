@@ -1,5 +1,7 @@
 use core::fmt;
 use std::fs;
+use std::io;
+use std::io::Write;
 
 pub struct Codegen {
     pub buf: String,
@@ -49,8 +51,13 @@ impl Codegen {
         self.buf.push_str("\n");
     }
 
-    pub fn flush(&mut self, filename: &str) {
-        fs::write(filename, &self.buf).expect("Unable to write file");
+    /// Writes the assembly to `filename`, `-` being the standard output.
+    pub fn flush(&mut self, filename: &str) -> io::Result<()> {
+        if filename == "-" {
+            return io::stdout().write_all(self.buf.as_bytes());
+        }
+
+        fs::write(filename, &self.buf)
     }
 
     pub fn clear(&mut self) {
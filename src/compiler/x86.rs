@@ -1,21 +1,48 @@
-use crate::ast::{ArithExpr, Ast, If, Loop, LoopKind, Return, Unary, Variable};
+use crate::ast::{
+    ArithExpr, Assign, Ast, Case, Function, FunctionCall, GlobalInit, GlobalVar, If, Jump,
+    JumpKind, Label, LiteralKind, Loop, LoopKind, MemberAccess, Return, Switch, Ternary, Unary,
+    Variable,
+};
 use crate::codegen::writer::Codegen;
+use crate::diagnostic::{Diagnostic, Severity};
 use crate::parser::localscope::LocalScope;
 use crate::tokenizer::tokentype::TokenType;
-use crate::util::append_str;
+use crate::types::Type;
+use crate::util::{append_str, x86_program_offset};
+
+static ARG_REGS: [&str; 6] = ["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+static ARG_REGS32: [&str; 6] = ["%edi", "%esi", "%edx", "%ecx", "%r8d", "%r9d"];
+static ARG_REGS16: [&str; 6] = ["%di", "%si", "%dx", "%cx", "%r8w", "%r9w"];
+static ARG_REGS8: [&str; 6] = ["%dil", "%sil", "%dl", "%cl", "%r8b", "%r9b"];
+
+// a switch jumps through a table rather than comparing against each case
+// when it has this many cases and at least half of the table is used
+const JUMP_TABLE_MIN_CASES: usize = 4;
 
 pub struct X86<'c> {
     pub gen: &'c mut Codegen,
     pub scope: LocalScope,
     pub label_suf_count: u16,
+    pub current_fn: String,
+    // where break and continue jump to, innermost loop last
+    pub break_labels: Vec<String>,
+    pub continue_labels: Vec<String>,
+    // the label numbers of the switches the current statement is in
+    pub switch_ids: Vec<u16>,
+    pub errors: Vec<Diagnostic>,
 }
 
 impl<'c> X86<'c> {
-    pub fn new(codegen: &'c mut Codegen, scope: LocalScope) -> Self {
+    pub fn new(codegen: &'c mut Codegen) -> Self {
         Self {
             gen: codegen,
-            scope,
+            scope: LocalScope::new(),
             label_suf_count: 0,
+            current_fn: String::new(),
+            break_labels: Vec::new(),
+            continue_labels: Vec::new(),
+            switch_ids: Vec::new(),
+            errors: Vec::new(),
         }
     }
 
@@ -32,47 +59,369 @@ impl<'c> X86<'c> {
                     self.compile(stmt);
                 }
             }
-            Ast::Literal(lit) => {
-                let mut val = "$".to_owned();
-                val.push_str(lit.val.as_str());
-                self.gen.icmd2ln("mov", val.as_str(), "%rax");
-            }
+            Ast::Literal(lit) => match lit.kind {
+                LiteralKind::String(_) => {
+                    let rip = append_str(&lit.val, "(%rip)", "");
+                    self.gen.icmd2ln("lea", rip.as_str(), "%rax");
+                }
+                _ => {
+                    let mut val = "$".to_owned();
+                    val.push_str(lit.val.as_str());
+                    self.gen.icmd2ln("mov", val.as_str(), "%rax");
+                }
+            },
             Ast::ArithExpr(arith) => self.arithmetic(arith),
+            Ast::Ternary(t) => self.ternary(t),
+            Ast::Cast(c) => {
+                self.compile(*c.expr);
+                self.extend(&c.ty);
+            }
             Ast::If(i) => self.if_stmt(i),
+            Ast::Switch(s) => self.switch_stmt(s),
+            Ast::Case(c) => self.case(c),
             Ast::Loop(l) => self.loop_stmt(l),
-            Ast::FunctionCall(f) => {}
+            Ast::Jump(j) => self.jump(j),
+            Ast::Label(l) => self.label(l),
+            Ast::FunctionCall(f) => self.func_call(f),
+            Ast::Function(f) => self.function(f),
+            Ast::GlobalVar(g) => self.global_var(g),
             Ast::Unary(u) => self.unary(u),
             Ast::Variable(v) => self.variable(v),
+            Ast::MemberAccess(m) => self.member_access(m),
+            Ast::Assign(a) => self.assign(a),
+            Ast::Declaration(d) => {
+                for init in d.inits {
+                    self.compile(init);
+                }
+            }
             Ast::Return(r) => self.return_stmt(r),
         }
     }
+
+    pub fn function(&mut self, f: Function) {
+        self.scope = f.scope;
+        self.current_fn = f.name;
+
+        self.gen.iwriteln(".text");
+        if !f.is_static {
+            self.gen
+                .iwriteln(append_str(".globl ", &self.current_fn, "").as_str());
+        }
+        self.gen
+            .writeln(append_str(&self.current_fn, ":", "").as_str());
+
+        // Prologue
+        let offset = x86_program_offset(self.scope.offset);
+        self.gen.icmd1ln("push", "%rbp");
+        self.gen.icmd2ln("mov", "%rsp", "%rbp");
+        self.gen.icmd2ln("sub", offset.as_str(), "%rsp");
+
+        // Save passed-by-register arguments to the stack, arguments beyond
+        // the sixth were pushed by the caller right above the return address
+        for (i, id) in f.params.iter().enumerate() {
+            let (_, offset) = self.scope.offset_by_id(*id);
+            let offset_rbp = append_str("", offset, "(%rbp)");
+            let size = self.scope.type_by_id(*id).unwrap().size();
+            if i < ARG_REGS.len() {
+                let reg = match size {
+                    1 => ARG_REGS8[i],
+                    2 => ARG_REGS16[i],
+                    4 => ARG_REGS32[i],
+                    _ => ARG_REGS[i],
+                };
+                self.gen.icmd2ln("mov", reg, offset_rbp.as_str());
+            } else {
+                let stack_arg = append_str("", 16 + 8 * (i - ARG_REGS.len()), "(%rbp)");
+                self.gen.icmd2ln("mov", stack_arg.as_str(), "%rax");
+                self.store_rax(size, offset_rbp.as_str());
+            }
+        }
+
+        self.compile(*f.body);
+
+        // Epilogue
+        self.gen.writeln(self.return_label(":").as_str());
+        self.gen.icmd2ln("mov", "%rbp", "%rsp");
+        self.gen.icmd1ln("pop", "%rbp");
+        self.gen.iwriteln("ret");
+    }
+
+    fn return_label(&self, suffix: &str) -> String {
+        append_str(".L.return.", &self.current_fn, suffix)
+    }
+
+    /// The assembly label of the label `name`, which only has to be unique
+    /// within its function.
+    fn user_label(&self, name: &str, suffix: &str) -> String {
+        format!(".L.label.{}.{}{}", self.current_fn, name, suffix)
+    }
+
+    /// Emits a global into .data, or into .bss when it is all zeros. The
+    /// contents of string literals go to .rodata.
+    pub fn global_var(&mut self, g: GlobalVar) {
+        let zeroed = !g.read_only && g.data.iter().all(|d| matches!(d, GlobalInit::Value(0)));
+
+        let section = match (g.read_only, zeroed) {
+            (true, _) => ".section .rodata",
+            (false, true) => ".bss",
+            (false, false) => ".data",
+        };
+        self.gen.iwriteln(section);
+        if !g.is_static {
+            self.gen
+                .iwriteln(append_str(".globl ", &g.name, "").as_str());
+        }
+        self.gen
+            .iwriteln(append_str(".align ", g.ty.align(), "").as_str());
+        self.gen.writeln(append_str(&g.name, ":", "").as_str());
+
+        if zeroed {
+            self.gen
+                .iwriteln(append_str(".zero ", g.ty.size(), "").as_str());
+            return;
+        }
+
+        let elem_size = g.ty.element().size();
+        let directive = match elem_size {
+            1 => ".byte ",
+            2 => ".short ",
+            4 => ".long ",
+            _ => ".quad ",
+        };
+
+        for d in g.data.iter() {
+            let val = match d {
+                GlobalInit::Value(val) => val.to_string(),
+                GlobalInit::Address(label, 0) => label.clone(),
+                GlobalInit::Address(label, offset) => format!("{}{:+}", label, offset),
+            };
+            self.gen.iwriteln(append_str(directive, val, "").as_str());
+        }
+
+        let rest = g.ty.size() - elem_size * g.data.len() as i32;
+        if rest > 0 {
+            self.gen.iwriteln(append_str(".zero ", rest, "").as_str());
+        }
+    }
+
+    pub fn func_call(&mut self, f: FunctionCall) {
+        let nargs = f.args.len();
+        let stack_args = nargs.saturating_sub(ARG_REGS.len()) as i64;
+
+        // %rsp must be 16-byte aligned at the call instruction, pad the
+        // stack when the pending pushes plus stack arguments are odd
+        let padding = (self.gen.depth + stack_args) % 2;
+        if padding == 1 {
+            self.gen.icmd2ln("sub", "$8", "%rsp");
+            self.gen.depth += 1;
+        }
+
+        // push in reverse so the first argument ends up on top of the stack
+        for arg in f.args.into_iter().rev() {
+            self.compile(arg);
+            self.gen.ipush();
+        }
+
+        for reg in ARG_REGS.iter().take(nargs) {
+            self.gen.ipop(reg);
+        }
+
+        self.gen.icmd2ln("mov", "$0", "%rax");
+        self.gen.icmd1ln("call", f.name.as_str());
+
+        // the upper bits of a narrow return value are unspecified
+        self.extend(f.ty.as_ref().unwrap());
+
+        let cleanup = stack_args + padding;
+        if cleanup > 0 {
+            let bytes = append_str("$", cleanup * 8, "");
+            self.gen.icmd2ln("add", bytes.as_str(), "%rsp");
+            self.gen.depth -= cleanup;
+        }
+    }
+
     pub fn arithmetic(&mut self, arith: ArithExpr) {
+        if matches!(
+            arith.token.kind,
+            TokenType::TokenAndAnd | TokenType::TokenPipePipe
+        ) {
+            return self.logical(arith);
+        }
+        if arith.token.is(TokenType::TokenComma) {
+            self.compile(*arith.left);
+            return self.compile(*arith.right);
+        }
+
+        let left_ty = arith.left.ty().unwrap().clone();
+        let right_ty = arith.right.ty().unwrap().clone();
+
         self.compile(*arith.right);
         self.gen.ipush();
         self.compile(*arith.left);
         self.gen.ipop("%rdi");
 
-        match arith.token.kind {
-            TokenType::TokenPlus => self.gen.icmd2ln("add", "%rdi", "%rax"),
-            TokenType::TokenMinus => self.gen.icmd2ln("sub", "%rdi", "%rax"),
-            TokenType::TokenStar => self.gen.icmd2ln("imul", "%rdi", "%rax"),
+        self.binary_op(&arith.token.kind, &left_ty, &right_ty);
+        // results narrower than 64 bits wrap around in their type
+        if !Self::is_comparison(&arith.token.kind) {
+            self.extend(arith.ty.as_ref().unwrap());
+        }
+    }
+
+    fn is_comparison(op: &TokenType) -> bool {
+        matches!(
+            op,
             TokenType::TokenLess
-            | TokenType::TokenLessEqual
-            | TokenType::TokenGreater
-            | TokenType::TokenGreaterEqual
-            | TokenType::TokenBangEqual
-            | TokenType::TokenEqualEqual => self.comparison(arith.token.kind),
-            TokenType::TokenSlash => {
+                | TokenType::TokenLessEqual
+                | TokenType::TokenGreater
+                | TokenType::TokenGreaterEqual
+                | TokenType::TokenBangEqual
+                | TokenType::TokenEqualEqual
+        )
+    }
+
+    /// Applies the binary operator `op` to the left operand in %rax and
+    /// the right one in %rdi, leaving the result in %rax. Integer operands
+    /// have their common type, which decides if the operation is signed.
+    fn binary_op(&mut self, op: &TokenType, left_ty: &Type, right_ty: &Type) {
+        let unsigned = left_ty.is_unsigned() || left_ty.is_pointer();
+        match op {
+            TokenType::TokenPlus => {
+                self.scale_pointer_arith(left_ty, right_ty);
+                self.gen.icmd2ln("add", "%rdi", "%rax");
+            }
+            TokenType::TokenMinus => match (left_ty.base(), right_ty.base()) {
+                // the distance between two pointers is counted in elements,
+                // elements of an empty struct all share one address
+                (Some(base), Some(_)) if base.size() == 0 => {
+                    self.gen.icmd2ln("sub", "%rdi", "%rax");
+                }
+                (Some(base), Some(_)) => {
+                    self.gen.icmd2ln("sub", "%rdi", "%rax");
+                    let size = append_str("$", base.size(), "");
+                    self.gen.icmd2ln("mov", size.as_str(), "%rdi");
+                    self.gen.icmdln("cqo");
+                    self.gen.icmd1ln("idiv", "%rdi");
+                }
+                _ => {
+                    self.scale_pointer_arith(left_ty, right_ty);
+                    self.gen.icmd2ln("sub", "%rdi", "%rax");
+                }
+            },
+            TokenType::TokenStar => self.gen.icmd2ln("imul", "%rdi", "%rax"),
+            _ if Self::is_comparison(op) => self.comparison(op.clone(), unsigned),
+            TokenType::TokenSlash => self.divide(unsigned),
+            // the remainder of the division is left in %rdx
+            TokenType::TokenPercent => {
+                self.divide(unsigned);
+                self.gen.icmd2ln("mov", "%rdx", "%rax");
+            }
+            TokenType::TokenAddr => self.gen.icmd2ln("and", "%rdi", "%rax"),
+            TokenType::TokenCaret => self.gen.icmd2ln("xor", "%rdi", "%rax"),
+            TokenType::TokenPipe => self.gen.icmd2ln("or", "%rdi", "%rax"),
+            // the shift count has to be in %cl
+            TokenType::TokenLessLess => {
+                self.gen.icmd2ln("mov", "%rdi", "%rcx");
+                self.gen.icmd2ln("shl", "%cl", "%rax");
+            }
+            TokenType::TokenGreaterGreater => {
+                self.gen.icmd2ln("mov", "%rdi", "%rcx");
+                match unsigned {
+                    true => self.gen.icmd2ln("shr", "%cl", "%rax"),
+                    false => self.gen.icmd2ln("sar", "%cl", "%rax"),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Divides %rax by %rdi, the dividend is extended into %rdx by its
+    /// sign or by zeros.
+    fn divide(&mut self, unsigned: bool) {
+        match unsigned {
+            true => {
+                self.gen.icmd2ln("mov", "$0", "%rdx");
+                self.gen.icmd1ln("div", "%rdi");
+            }
+            false => {
                 self.gen.icmdln("cqo");
                 self.gen.icmd1ln("idiv", "%rdi");
             }
+        }
+    }
+
+    /// `&&` and `||` evaluate their right operand only when the left one
+    /// does not decide the result, which is 0 or 1.
+    fn logical(&mut self, arith: ArithExpr) {
+        let label_id = self.incr_label_suffix_counter();
+        let (jump, short_circuit) = match arith.token.kind {
+            TokenType::TokenAndAnd => ("je", 0),
+            _ => ("jne", 1),
+        };
+        let label_short = append_str(".L.short.", label_id, "");
+        let label_end = append_str(".L.end.", label_id, "");
+
+        self.compile(*arith.left);
+        self.gen.icmd2ln("cmp", "$0", "%rax");
+        self.gen.icmd1ln(jump, label_short.as_str());
+
+        self.compile(*arith.right);
+        self.gen.icmd2ln("cmp", "$0", "%rax");
+        self.gen.icmd1ln(jump, label_short.as_str());
+
+        let result = append_str("$", 1 - short_circuit, "");
+        self.gen.icmd2ln("mov", result.as_str(), "%rax");
+        self.gen.icmd1ln("jmp", label_end.as_str());
+
+        self.gen.writeln(append_str(&label_short, ":", "").as_str());
+        let result = append_str("$", short_circuit, "");
+        self.gen.icmd2ln("mov", result.as_str(), "%rax");
+        self.gen.writeln(append_str(&label_end, ":", "").as_str());
+    }
+
+    /// Only the operand selected by the condition is evaluated, either one
+    /// leaves its value in %rax.
+    fn ternary(&mut self, t: Ternary) {
+        let label_id = self.incr_label_suffix_counter();
+        let label_else = append_str(".L.else.", label_id, "");
+        let label_end = append_str(".L.end.", label_id, "");
+
+        self.compile(*t.cond);
+        self.gen.icmd2ln("cmp", "$0", "%rax");
+        self.gen.icmd1ln("je", label_else.as_str());
+
+        self.compile(*t.then);
+        self.gen.icmd1ln("jmp", label_end.as_str());
+
+        self.gen.writeln(append_str(&label_else, ":", "").as_str());
+        self.compile(*t.alt);
+        self.gen.writeln(append_str(&label_end, ":", "").as_str());
+    }
+
+    /// Scales the integer operand of `pointer +/- integer` by the size of
+    /// the pointee, the left operand is in %rax and the right one in %rdi.
+    fn scale_pointer_arith(&mut self, left: &Type, right: &Type) {
+        match (left.base(), right.base()) {
+            (Some(base), None) => {
+                let size = append_str("$", base.size(), "");
+                self.gen.icmd2ln("imul", size.as_str(), "%rdi");
+            }
+            (None, Some(base)) => {
+                let size = append_str("$", base.size(), "");
+                self.gen.icmd2ln("imul", size.as_str(), "%rax");
+            }
             _ => {}
         }
     }
 
-    pub fn comparison(&mut self, token_type: TokenType) {
+    /// Pointers and unsigned integers are ordered with the below/above
+    /// conditions.
+    pub fn comparison(&mut self, token_type: TokenType, unsigned: bool) {
         self.gen.icmd2ln("cmp", "%rdi", "%rax");
         match token_type {
+            TokenType::TokenLessEqual if unsigned => self.gen.icmd1ln("setbe", "%al"),
+            TokenType::TokenLess if unsigned => self.gen.icmd1ln("setb", "%al"),
+            TokenType::TokenGreater if unsigned => self.gen.icmd1ln("seta", "%al"),
+            TokenType::TokenGreaterEqual if unsigned => self.gen.icmd1ln("setae", "%al"),
             TokenType::TokenLessEqual => self.gen.icmd1ln("setle", "%al"),
             TokenType::TokenLess => self.gen.icmd1ln("setl", "%al"),
             TokenType::TokenGreater => self.gen.icmd1ln("setg", "%al"),
@@ -89,39 +438,187 @@ impl<'c> X86<'c> {
             TokenType::TokenMinus => {
                 self.compile(*u.right);
                 self.gen.icmd1ln("neg", "%rax");
+                self.extend(u.ty.as_ref().unwrap());
             }
             TokenType::TokenPlus => {
                 self.compile(*u.right);
             }
-            TokenType::TokenAddr => {}
+            TokenType::TokenTilde => {
+                self.compile(*u.right);
+                self.gen.icmd1ln("not", "%rax");
+                self.extend(u.ty.as_ref().unwrap());
+            }
+            TokenType::TokenBang => {
+                self.compile(*u.right);
+                self.gen.icmd2ln("cmp", "$0", "%rax");
+                self.gen.icmd1ln("sete", "%al");
+                self.gen.icmd2ln("movzb", "%al", "%rax");
+            }
+            TokenType::TokenAddr => self.gen_lvalue(*u.right),
+            TokenType::TokenPlusPlus | TokenType::TokenMinusMinus => self.postfix_incr(u),
             TokenType::TokenDeref => {
                 self.compile(*u.right);
-                self.gen.icmd2ln("mov", "(%rax)", "%rax");
+                self.load(u.ty.as_ref().unwrap());
             }
             _ => {}
         }
     }
 
     pub fn variable(&mut self, v: Variable) {
-        self.gen_address(v.id, v.token.kind);
+        self.gen_address(&v);
+        self.load(v.ty.as_ref().unwrap());
+    }
 
-        match v.assign {
-            None => {
-                self.gen.icmd2ln("mov", "(%rax)", "%rax");
+    pub fn member_access(&mut self, m: MemberAccess) {
+        let ty = m.ty.clone().unwrap();
+        self.gen_lvalue(Ast::MemberAccess(m));
+        self.load(&ty);
+    }
+
+    pub fn assign(&mut self, a: Assign) {
+        if let Some(op) = a.token.kind.compound_op() {
+            return self.compound_assign(op, a);
+        }
+
+        self.gen_lvalue(*a.left);
+        self.gen.ipush();
+        self.compile(*a.right);
+        self.store(a.ty.as_ref().unwrap());
+    }
+
+    /// `x op= y`, the address of x is computed once and kept on the stack
+    /// while y is evaluated. Integers are operated on in the common type
+    /// y was converted to, except for shifts which keep the type of x.
+    fn compound_assign(&mut self, op: TokenType, a: Assign) {
+        let ty = a.ty.clone().unwrap();
+        let right_ty = a.right.ty().unwrap().clone();
+        let op_ty = match op {
+            TokenType::TokenLessLess | TokenType::TokenGreaterGreater => ty.promoted(),
+            _ if ty.is_integer() => right_ty.clone(),
+            _ => ty.clone(),
+        };
+
+        self.gen_lvalue(*a.left);
+        self.gen.ipush();
+        self.compile(*a.right);
+        self.gen.icmd2ln("mov", "%rax", "%rdi");
+        self.gen.icmd2ln("mov", "(%rsp)", "%rax");
+        self.load(&ty);
+        self.extend(&op_ty);
+
+        self.binary_op(&op, &op_ty, &right_ty);
+        self.store(&ty);
+        // the value is that of x, truncated to its type
+        self.extend(&ty);
+    }
+
+    /// `x++` and `x--`, the old value of x is left in %rax.
+    fn postfix_incr(&mut self, u: Unary) {
+        let ty = u.ty.clone().unwrap();
+        let delta = match ty.base() {
+            Some(base) => base.size(),
+            None => 1,
+        };
+        let delta = match u.token.kind {
+            TokenType::TokenPlusPlus => append_str("$", delta, ""),
+            _ => append_str("$", -delta, ""),
+        };
+
+        self.gen_lvalue(*u.right);
+        self.gen.icmd2ln("mov", "%rax", "%rdi");
+        self.load(&ty);
+        self.gen.icmd2ln("mov", "%rax", "%rdx");
+        self.gen.icmd2ln("add", delta.as_str(), "%rax");
+        self.store_rax(ty.size(), "(%rdi)");
+        self.gen.icmd2ln("mov", "%rdx", "%rax");
+    }
+
+    /// Computes the address of an lvalue into %rax.
+    fn gen_lvalue(&mut self, node: Ast) {
+        match node {
+            Ast::Variable(v) => self.gen_address(&v),
+            Ast::Unary(u) if u.token.is(TokenType::TokenDeref) => self.compile(*u.right),
+            Ast::MemberAccess(m) => {
+                self.gen_lvalue(*m.left);
+                let offset = append_str("$", m.offset, "");
+                self.gen.icmd2ln("add", offset.as_str(), "%rax");
             }
-            Some(assign) => {
-                self.gen.ipush();
-                self.compile(*assign);
-                self.gen.ipop("%rdi");
-                self.gen.icmd2ln("mov", "%rax", "(%rdi)");
+            _ => self.errors.push(Diagnostic::new(
+                Severity::Error,
+                "internal error: expression is not an lvalue".to_string(),
+            )),
+        }
+    }
+
+    /// Loads the value of type `ty` at the address in %rax into %rax,
+    /// sign or zero extending it to 64 bits.
+    fn load(&mut self, ty: &Type) {
+        match ty {
+            // the address of an array or a struct is its value
+            Type::Array(_, _) | Type::Function(_, _) | Type::Struct(_) => {}
+            Type::Char => self.gen.icmd2ln("movsbq", "(%rax)", "%rax"),
+            Type::Short => self.gen.icmd2ln("movswq", "(%rax)", "%rax"),
+            Type::Int => self.gen.icmd2ln("movslq", "(%rax)", "%rax"),
+            Type::UChar => self.gen.icmd2ln("movzbl", "(%rax)", "%eax"),
+            Type::UShort => self.gen.icmd2ln("movzwl", "(%rax)", "%eax"),
+            // writing a 32-bit register clears the upper half
+            Type::UInt => self.gen.icmd2ln("mov", "(%rax)", "%eax"),
+            _ => self.gen.icmd2ln("mov", "(%rax)", "%rax"),
+        }
+    }
+
+    /// Stores %rax to the address on top of the stack. Structs are copied
+    /// byte by byte from the address in %rax.
+    fn store(&mut self, ty: &Type) {
+        self.gen.ipop("%rdi");
+
+        if let Type::Struct(_) = ty {
+            for i in 0..ty.size() {
+                let src = append_str("", i, "(%rax)");
+                let dest = append_str("", i, "(%rdi)");
+                self.gen.icmd2ln("mov", src.as_str(), "%r8b");
+                self.gen.icmd2ln("mov", "%r8b", dest.as_str());
             }
+            return;
+        }
+
+        self.store_rax(ty.size(), "(%rdi)");
+    }
+
+    /// Converts %rax to `ty` by truncating it to the size of `ty` and sign
+    /// or zero extending the low bits back to 64 bits.
+    fn extend(&mut self, ty: &Type) {
+        match ty {
+            Type::Char => self.gen.icmd2ln("movsbq", "%al", "%rax"),
+            Type::Short => self.gen.icmd2ln("movswq", "%ax", "%rax"),
+            Type::Int => self.gen.icmd2ln("movslq", "%eax", "%rax"),
+            Type::UChar => self.gen.icmd2ln("movzbl", "%al", "%eax"),
+            Type::UShort => self.gen.icmd2ln("movzwl", "%ax", "%eax"),
+            Type::UInt => self.gen.icmd2ln("mov", "%eax", "%eax"),
+            _ => {}
+        }
+    }
+
+    fn store_rax(&mut self, size: i32, dest: &str) {
+        match size {
+            1 => self.gen.icmd2ln("mov", "%al", dest),
+            2 => self.gen.icmd2ln("mov", "%ax", dest),
+            4 => self.gen.icmd2ln("mov", "%eax", dest),
+            _ => self.gen.icmd2ln("mov", "%rax", dest),
         }
     }
 
-    fn gen_address(&mut self, id: i8, token_type: TokenType) {
-        let (found, offset) = self.scope.offset_by_id(id);
+    fn gen_address(&mut self, v: &Variable) {
+        if v.is_global {
+            let rip = append_str(&v.val, "(%rip)", "");
+            self.gen.icmd2ln("lea", rip.as_str(), "%rax");
+            return;
+        }
+
+        let (found, offset) = self.scope.offset_by_id(v.id);
         if !found {
-            eprintln!("variable of id {} not found", offset);
+            let msg = format!("internal error: no stack slot for '{}'", v.val);
+            self.errors.push(Diagnostic::error(&v.token, msg));
             return;
         }
 
@@ -136,7 +633,8 @@ impl<'c> X86<'c> {
             Some(r) => self.compile(*r),
         }
 
-        self.gen.icmd1ln("jmp", ".L.return");
+        let label = self.return_label("");
+        self.gen.icmd1ln("jmp", label.as_str());
     }
 
     fn if_stmt(&mut self, i: If) {
@@ -163,11 +661,157 @@ impl<'c> X86<'c> {
             .writeln(append_str(".L.end.", label_id, ":").as_str())
     }
 
+    /// Jumps to the case matching the condition, or to `default` or past
+    /// the end when there is none. `break` in the body leaves the switch.
+    fn switch_stmt(&mut self, s: Switch) {
+        let label_id = self.incr_label_suffix_counter();
+        let label_end = append_str(".L.end.", label_id, "");
+
+        let otherwise = match s.cases.iter().position(|c| c.expr.is_none()) {
+            Some(index) => Self::case_label(label_id, index, ""),
+            None => label_end.clone(),
+        };
+        let values: Vec<(i64, usize)> = s
+            .cases
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.expr.is_some())
+            .map(|(i, c)| (c.value, i))
+            .collect();
+
+        self.compile(*s.cond);
+        match Self::table_range(&values) {
+            Some((min, len)) => self.jump_table(label_id, &values, min, len, &otherwise),
+            None => {
+                for (value, index) in values.iter() {
+                    let value = append_str("$", value, "");
+                    self.gen.icmd2ln("mov", value.as_str(), "%rdi");
+                    self.gen.icmd2ln("cmp", "%rdi", "%rax");
+                    let label = Self::case_label(label_id, *index, "");
+                    self.gen.icmd1ln("je", label.as_str());
+                }
+            }
+        }
+        self.gen.icmd1ln("jmp", otherwise.as_str());
+
+        self.break_labels.push(label_end.clone());
+        self.switch_ids.push(label_id);
+        self.compile(*s.body);
+        self.switch_ids.pop();
+        self.break_labels.pop();
+
+        self.gen.writeln(append_str(&label_end, ":", "").as_str());
+    }
+
+    /// The lowest case value and the number of entries of a jump table
+    /// from it to the highest one, `None` when the values are too few or
+    /// too sparse for a table.
+    fn table_range(values: &[(i64, usize)]) -> Option<(i64, usize)> {
+        if values.len() < JUMP_TABLE_MIN_CASES {
+            return None;
+        }
+
+        let min = values.iter().map(|(v, _)| *v).min()?;
+        let max = values.iter().map(|(v, _)| *v).max()?;
+        let len = max as i128 - min as i128 + 1;
+        match len <= 2 * values.len() as i128 {
+            true => Some((min, len as usize)),
+            false => None,
+        }
+    }
+
+    /// Jumps through a table of the offsets of the case labels from the
+    /// table, indexed by the condition in %rax minus `min`. Values out of
+    /// the table and holes in it go to `otherwise`.
+    fn jump_table(
+        &mut self,
+        label_id: u16,
+        values: &[(i64, usize)],
+        min: i64,
+        len: usize,
+        otherwise: &str,
+    ) {
+        let table = append_str(".L.table.", label_id, "");
+
+        let first = append_str("$", min, "");
+        self.gen.icmd2ln("mov", first.as_str(), "%rdi");
+        self.gen.icmd2ln("sub", "%rdi", "%rax");
+        // below min wraps around to a large unsigned index
+        let last = append_str("$", len - 1, "");
+        self.gen.icmd2ln("cmp", last.as_str(), "%rax");
+        self.gen.icmd1ln("ja", otherwise);
+
+        let rip = append_str(&table, "(%rip)", "");
+        self.gen.icmd2ln("lea", rip.as_str(), "%rdi");
+        self.gen.icmd2ln("movslq", "(%rdi,%rax,4)", "%rax");
+        self.gen.icmd2ln("add", "%rdi", "%rax");
+        self.gen.icmd1ln("jmp", "*%rax");
+
+        let mut entries = vec![otherwise.to_string(); len];
+        for (value, index) in values.iter() {
+            entries[(*value as i128 - min as i128) as usize] =
+                Self::case_label(label_id, *index, "");
+        }
+
+        self.gen.iwriteln(".section .rodata");
+        self.gen.iwriteln(".align 4");
+        self.gen.writeln(append_str(&table, ":", "").as_str());
+        for entry in entries.iter() {
+            let offset = format!(".long {}-{}", entry, table);
+            self.gen.iwriteln(offset.as_str());
+        }
+        self.gen.iwriteln(".text");
+    }
+
+    fn case(&mut self, c: Case) {
+        match self.switch_ids.last() {
+            Some(label_id) => {
+                let label = Self::case_label(*label_id, c.index, ":");
+                self.gen.writeln(label.as_str());
+            }
+            None => self.errors.push(Diagnostic::new(
+                Severity::Error,
+                "internal error: case label outside of a switch".to_string(),
+            )),
+        }
+        self.compile(*c.stmt);
+    }
+
+    fn case_label(label_id: u16, index: usize, suffix: &str) -> String {
+        format!(".L.case.{}.{}{}", label_id, index, suffix)
+    }
+
     fn loop_stmt(&mut self, i: Loop) {
         match i.kind {
             LoopKind::For | LoopKind::While => self.for_stmt(i),
-            LoopKind::DoWhile => {}
+            LoopKind::DoWhile => self.do_while_stmt(i),
+        }
+    }
+
+    /// The condition is tested after the body, which `continue` jumps to.
+    fn do_while_stmt(&mut self, i: Loop) {
+        let label_id = self.incr_label_suffix_counter();
+
+        let label_begin = append_str(".L.begin.", label_id, "");
+        let label_end = append_str(".L.end.", label_id, "");
+        let label_continue = append_str(".L.continue.", label_id, "");
+
+        self.gen.writeln(append_str(&label_begin, ":", "").as_str());
+
+        self.break_labels.push(label_end.clone());
+        self.continue_labels.push(label_continue.clone());
+        self.compile(*i.then);
+        self.break_labels.pop();
+        self.continue_labels.pop();
+
+        self.gen
+            .writeln(append_str(&label_continue, ":", "").as_str());
+        if let Some(condition) = i.cond {
+            self.compile(*condition);
+            self.gen.icmd2ln("cmp", "$0", "%rax");
+            self.gen.icmd1ln("jne", &label_begin);
         }
+        self.gen.writeln(append_str(&label_end, ":", "").as_str());
     }
 
     fn for_stmt(&mut self, i: Loop) {
@@ -175,6 +819,7 @@ impl<'c> X86<'c> {
 
         let label_begin = append_str(".L.begin.", &label_id, "");
         let label_end = append_str(".L.end.", &label_id, "");
+        let label_continue = append_str(".L.continue.", label_id, "");
 
         match i.init {
             None => {}
@@ -185,6 +830,9 @@ impl<'c> X86<'c> {
 
         self.gen.writeln(append_str(&label_begin, ":", "").as_str());
 
+        self.break_labels.push(label_end.clone());
+        self.continue_labels.push(label_continue.clone());
+
         match i.cond {
             None => {}
             Some(condition) => {
@@ -196,6 +844,11 @@ impl<'c> X86<'c> {
 
         self.compile(*i.then);
 
+        self.break_labels.pop();
+        self.continue_labels.pop();
+
+        self.gen
+            .writeln(append_str(&label_continue, ":", "").as_str());
         match i.incr {
             None => {}
             Some(increment) => self.compile(*increment),
@@ -204,4 +857,25 @@ impl<'c> X86<'c> {
         self.gen.icmd1ln("jmp", &label_begin);
         self.gen.writeln(append_str(&label_end, ":", "").as_str());
     }
+
+    fn jump(&mut self, j: Jump) {
+        let target = match j.kind {
+            JumpKind::Break => self.break_labels.last().cloned(),
+            JumpKind::Continue => self.continue_labels.last().cloned(),
+            JumpKind::Goto(name) => Some(self.user_label(&name, "")),
+        };
+
+        match target {
+            Some(label) => self.gen.icmd1ln("jmp", label.as_str()),
+            None => {
+                let msg = "internal error: jump outside of a loop".to_string();
+                self.errors.push(Diagnostic::error(&j.token, msg));
+            }
+        }
+    }
+
+    fn label(&mut self, l: Label) {
+        self.gen.writeln(self.user_label(&l.name, ":").as_str());
+        self.compile(*l.stmt);
+    }
 }
@@ -0,0 +1,133 @@
+use std::fmt;
+use std::fmt::Formatter;
+
+use crate::tokenizer::token::Token;
+use crate::tokenizer::tokentype::TokenType;
+
+mod render;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// A range of source text on a single line, `line` and `col` are 1-based.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub col: usize,
+    pub len: usize,
+}
+
+/// A message about the program being compiled, with the place it is about
+/// when there is one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub path: Option<String>,
+    pub span: Option<Span>,
+    // the source line the span is on, shown under the message
+    pub source_line: Option<String>,
+    // further places the message is about, e.g. the macros it was
+    // expanded from
+    pub notes: Vec<Diagnostic>,
+}
+
+impl Span {
+    pub(crate) fn from_token(tok: &Token) -> Self {
+        Self {
+            line: tok.line,
+            col: tok.col,
+            // the text of an invalid token is the reason it is invalid
+            len: match tok.kind {
+                TokenType::TokenError => 1,
+                _ => tok.val.chars().count().max(1),
+            },
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: String) -> Self {
+        Self {
+            severity,
+            message,
+            path: None,
+            span: None,
+            source_line: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// An error about the source text of `tok`, in the file it was read from.
+    /// A token coming from a macro gets a note for every macro invocation it
+    /// was expanded from, innermost first.
+    pub(crate) fn error(tok: &Token, message: String) -> Self {
+        let mut diagnostic = Self::at(tok, Severity::Error, message);
+
+        let mut origin = tok.origin.clone();
+        while let Some(name) = origin {
+            let msg = format!("in expansion of macro '{}'", name.val);
+            diagnostic.notes.push(Self::at(&name, Severity::Note, msg));
+            origin = name.origin.clone();
+        }
+
+        diagnostic
+    }
+
+    fn at(tok: &Token, severity: Severity, message: String) -> Self {
+        Self {
+            path: Some(tok.file.name.clone()),
+            span: Some(Span::from_token(tok)),
+            source_line: tok.file.line(tok.line).map(|line| line.to_string()),
+            ..Self::new(severity, message)
+        }
+    }
+
+    pub fn with_path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    /// Where the diagnostic is about, `path:line:col`, `path` or the
+    /// compiler itself when there is no file at all.
+    fn location(&self) -> String {
+        match (&self.path, &self.span) {
+            (Some(path), Some(span)) => format!("{}:{}:{}", path, span.line, span.col),
+            (Some(path), None) => path.clone(),
+            (None, Some(span)) => format!("{}:{}", span.line, span.col),
+            (None, None) => "chibicc".to_string(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// Formats as the single line `path:line:col: severity: message`, see
+/// `render` for the source snippet.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}: {}",
+            self.location(),
+            self.severity,
+            self.message
+        )
+    }
+}
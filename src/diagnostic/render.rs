@@ -0,0 +1,61 @@
+use crate::diagnostic::{Diagnostic, Severity};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BOLD_RED: &str = "\x1b[1;31m";
+const BOLD_GREEN: &str = "\x1b[1;32m";
+const BOLD_MAGENTA: &str = "\x1b[1;35m";
+const BOLD_CYAN: &str = "\x1b[1;36m";
+
+impl Diagnostic {
+    /// Renders the diagnostic like gcc and clang do, the message followed by
+    /// the source line and a `^~~~` marker under the span, then the notes.
+    /// `color` adds ANSI escapes for terminals.
+    pub fn render(&self, color: bool) -> String {
+        let paint = |code: &str, text: String| -> String {
+            match color {
+                true => format!("{}{}{}", code, text, RESET),
+                false => text,
+            }
+        };
+
+        let severity_color = match self.severity {
+            Severity::Error => BOLD_RED,
+            Severity::Warning => BOLD_MAGENTA,
+            Severity::Note => BOLD_CYAN,
+        };
+
+        let mut out = format!(
+            "{} {} {}",
+            paint(BOLD, format!("{}:", self.location())),
+            paint(severity_color, format!("{}:", self.severity)),
+            paint(BOLD, self.message.clone()),
+        );
+
+        if let (Some(span), Some(line)) = (&self.span, &self.source_line) {
+            // tabs are kept so the marker lines up with the text above it
+            let indent: String = line
+                .chars()
+                .take(span.col - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            let rest = line.chars().count().saturating_sub(span.col - 1);
+            let width = span.len.min(rest).max(1);
+            let marker = format!("^{}", "~".repeat(width - 1));
+
+            out.push('\n');
+            out.push_str(line);
+            out.push('\n');
+            out.push_str(&indent);
+            out.push_str(&paint(BOLD_GREEN, marker));
+        }
+
+        for note in self.notes.iter() {
+            out.push('\n');
+            out.push_str(&note.render(color));
+        }
+
+        out
+    }
+}
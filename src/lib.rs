@@ -2,47 +2,138 @@ extern crate core;
 
 use crate::codegen::writer::Codegen;
 use crate::compiler::x86::X86;
+use crate::diagnostic::{Diagnostic, Severity};
 use crate::parser::Parser;
+use crate::preprocessor::print::print_tokens;
+use crate::preprocessor::Preprocessor;
+use crate::tokenizer::source::SourceFile;
+use crate::tokenizer::tokentype::TokenType;
+use crate::types::checker::TypeChecker;
 
 mod ast;
 mod codegen;
 mod compiler;
+pub mod diagnostic;
 mod parser;
+mod preprocessor;
 mod tokenizer;
+mod types;
 mod util;
 
-pub fn gen(input: &str, filename: &str) {
-    let mut codegen = Codegen::new();
-    codegen.iwriteln(".globl main");
-    codegen.writeln("main:");
+/// Settings of a compilation given on the command line.
+#[derive(Clone, Debug, Default)]
+pub struct CompileOptions {
+    /// Searched for included files, in order, after the directory of the
+    /// including file for `#include "..."`.
+    pub include_paths: Vec<String>,
+    /// `-D` and `-U` options, applied in order before the source is read.
+    pub macros: Vec<MacroDef>,
+    /// The time of `__DATE__` and `__TIME__` in seconds since 1970, the
+    /// current time when `None`. Set by `SOURCE_DATE_EPOCH` for
+    /// reproducible builds.
+    pub source_date_epoch: Option<u64>,
+}
 
-    let mut parser = Parser::new(input);
+/// A macro defined or undefined on the command line.
+#[derive(Clone, Debug)]
+pub enum MacroDef {
+    /// `-D name=body`, the body of `-D name` being `1`. The name may have a
+    /// parameter list.
+    Define(String, String),
+    /// `-U name`
+    Undef(String),
+}
 
-    let ast = parser.parse_program();
-    let offset = util::x86_program_offset(parser.offset());
-    let scope = parser.scope;
+/// Preprocesses the C source read from `path` and writes the result to
+/// `output`, like `-E`.
+pub fn preprocess(
+    input: &str,
+    path: &str,
+    output: &str,
+    opts: &CompileOptions,
+) -> Result<(), Vec<Diagnostic>> {
+    let mut preprocessor = Preprocessor::new(opts);
+    let tokens = preprocessor.preprocess(SourceFile::new(path, input));
 
-    prologue(&mut codegen, offset.as_str());
+    // invalid tokens are otherwise reported by the parser
+    let mut errors = preprocessor.errors;
+    errors.extend(
+        tokens
+            .iter()
+            .filter(|tok| tok.is(TokenType::TokenError))
+            .map(|tok| Diagnostic::error(tok, tok.val.clone())),
+    );
+    if !errors.is_empty() {
+        return Err(errors);
+    }
 
-    let mut x86 = X86::new(&mut codegen, scope);
-    x86.compile(ast);
+    let mut codegen = Codegen::new();
+    codegen.write(&print_tokens(&tokens));
+    flush(&mut codegen, output)
+}
 
-    epilogue(&mut codegen);
+/// Preprocesses the C source read from `path` and writes the `#define`
+/// lines of the macros defined at its end to `output`, like `-dM -E`.
+pub fn dump_macros(
+    input: &str,
+    path: &str,
+    output: &str,
+    opts: &CompileOptions,
+) -> Result<(), Vec<Diagnostic>> {
+    let mut preprocessor = Preprocessor::new(opts);
+    preprocessor.preprocess(SourceFile::new(path, input));
+    if !preprocessor.errors.is_empty() {
+        return Err(preprocessor.errors);
+    }
 
-    codegen.flush(filename);
+    let mut codegen = Codegen::new();
+    for def in preprocessor.definitions() {
+        codegen.writeln(&def);
+    }
+    flush(&mut codegen, output)
 }
 
-fn prologue(gen: &mut Codegen, offset: &str) {
-    // Prologue
-    gen.icmd1ln("push", "%rbp");
-    gen.icmd2ln("mov", "%rsp", "%rbp");
-    gen.icmd2ln("sub", offset, "%rsp");
+/// Compiles the C source read from `path` to assembly written to `output`,
+/// `-` being the standard output. Nothing is written when there are errors.
+pub fn gen(
+    input: &str,
+    path: &str,
+    output: &str,
+    opts: &CompileOptions,
+) -> Result<(), Vec<Diagnostic>> {
+    let mut codegen = Codegen::new();
+
+    let mut preprocessor = Preprocessor::new(opts);
+    let tokens = preprocessor.preprocess(SourceFile::new(path, input));
+    if !preprocessor.errors.is_empty() {
+        return Err(preprocessor.errors);
+    }
+
+    let mut parser = Parser::new(tokens);
+    let mut ast = parser.parse_program();
+    if !parser.errors.is_empty() {
+        return Err(parser.errors);
+    }
+
+    let mut checker = TypeChecker::new();
+    checker.check(&mut ast);
+    if !checker.errors.is_empty() {
+        return Err(checker.errors);
+    }
+
+    // errors of the backend are internal ones, which have no source position
+    let mut x86 = X86::new(&mut codegen);
+    x86.compile(ast);
+    if !x86.errors.is_empty() {
+        return Err(x86.errors.into_iter().map(|e| e.with_path(path)).collect());
+    }
+
+    flush(&mut codegen, output)
 }
 
-fn epilogue(gen: &mut Codegen) {
-    // epilogue
-    gen.writeln(".L.return:");
-    gen.icmd2ln("mov", "%rbp", "%rsp");
-    gen.icmd1ln("pop", "%rbp");
-    gen.iwriteln("ret");
+fn flush(codegen: &mut Codegen, output: &str) -> Result<(), Vec<Diagnostic>> {
+    codegen.flush(output).map_err(|e| {
+        let msg = format!("cannot write {}: {}", output, e);
+        vec![Diagnostic::new(Severity::Error, msg)]
+    })
 }
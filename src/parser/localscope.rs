@@ -1,24 +1,32 @@
+use crate::types::Type;
+use crate::util::checked_align_to;
+
+#[derive(Debug, Clone)]
 pub struct LocalScope {
-    pub local_count: i8,
+    pub local_count: usize,
     pub scope_depth: i8,
     pub locals: Vec<Local>,
+    pub active: Vec<usize>,
     pub offset: i32,
 }
 
+#[derive(Debug, Clone)]
 pub struct Local {
-    id: i8,
+    id: usize,
     name: String,
     depth: i8,
     offset: i32,
+    ty: Type,
 }
 
 impl Local {
-    pub fn new(name: String, depth: i8, id: i8) -> Self {
+    pub fn new(name: String, depth: i8, id: usize, ty: Type) -> Self {
         Self {
             name,
             depth,
             offset: 0,
             id,
+            ty,
         }
     }
 }
@@ -28,6 +36,7 @@ impl LocalScope {
         Self {
             local_count: 0,
             locals: Vec::new(),
+            active: Vec::new(),
             scope_depth: 0,
             offset: 0,
         }
@@ -37,105 +46,86 @@ impl LocalScope {
         self.scope_depth += 1;
     }
 
+    /// Closes the innermost block, hiding the locals declared in it from
+    /// name resolution. They keep their stack slot.
     pub fn end_scope(&mut self) -> u8 {
         self.scope_depth -= 1;
         let mut counts = 0;
-        while self.local_count > 0
-            && self
-                .locals
-                .get((self.local_count - 1) as usize)
-                .unwrap()
-                .depth
-                > self.scope_depth
-        {
+        while let Some(idx) = self.active.last() {
+            if self.locals[*idx].depth <= self.scope_depth {
+                break;
+            }
+
             counts += 1;
-            self.local_count -= 1
+            self.active.pop();
         }
         counts
     }
 
-    pub fn add_local_if_not_exist(&mut self, name: &str) -> i8 {
-        let (exist, id) = self.contains(name);
-        if exist {
-            return id;
-        }
-
-        self.add_local(name)
-    }
-
-    pub fn add_local(&mut self, name: &str) -> i8 {
+    pub fn add_local(&mut self, name: &str, ty: Type) -> usize {
         self.local_count += 1;
-        let local = Local::new(name.to_string(), self.scope_depth, self.local_count);
+        let local = Local::new(name.to_string(), self.scope_depth, self.local_count, ty);
+        self.active.push(self.locals.len());
         self.locals.push(local);
         self.local_count
     }
 
-    pub fn offset_by_id(&self, id: i8) -> (bool, i32) {
-        let mut counter = self.local_count - 1;
-
-        while counter >= 0 {
-            let l = self.locals.get(counter as usize).unwrap();
-            if l.depth != -1 && l.depth < self.scope_depth {
-                break;
-            }
+    fn local_by_id(&self, id: usize) -> Option<&Local> {
+        self.locals.iter().find(|l| l.id == id)
+    }
 
-            if l.id == id {
-                return (true, l.offset);
-            }
+    pub fn type_by_id(&self, id: usize) -> Option<&Type> {
+        self.local_by_id(id).map(|l| &l.ty)
+    }
 
-            counter -= 1;
+    pub fn offset_by_id(&self, id: usize) -> (bool, i32) {
+        match self.local_by_id(id) {
+            None => (false, 0),
+            Some(l) => (true, l.offset),
         }
-        (false, 0)
     }
 
-    pub fn contains(&self, name: &str) -> (bool, i8) {
-        let mut counter = self.local_count - 1;
-
-        while counter >= 0 {
-            let l = self.locals.get(counter as usize).unwrap();
-            if l.depth != -1 && l.depth < self.scope_depth {
+    /// Looks for `name` among the locals of the innermost block only.
+    pub fn contains(&self, name: &str) -> (bool, usize) {
+        for idx in self.active.iter().rev() {
+            let l = &self.locals[*idx];
+            if l.depth < self.scope_depth {
                 break;
             }
 
             if l.name.eq(name) {
                 return (true, l.id);
             }
-
-            counter -= 1;
         }
 
         (false, 0)
     }
 
-    pub fn resolve_local(&self, name: &str) -> Option<u8> {
-        let mut counter = self.local_count - 1;
-
-        while counter >= 0 {
-            let l = self.locals.get(counter as usize);
-            match l {
-                None => {}
-                Some(v) => {
-                    if v.name.eq(name) {
-                        return Some(counter as u8);
-                    }
-                }
-            }
-
-            counter -= 1;
-        }
-
-        return None;
+    /// Looks for `name` in every enclosing block, innermost first.
+    pub fn resolve_local(&self, name: &str) -> Option<usize> {
+        self.active
+            .iter()
+            .rev()
+            .map(|idx| &self.locals[*idx])
+            .find(|l| l.name.eq(name))
+            .map(|l| l.id)
     }
 
-    pub fn assign_offsets(&mut self) -> i32 {
-        let mut offset = 0;
-        for loc in self.locals.iter_mut() {
-            offset += 8;
+    /// Assigns the stack slots of the locals, `None` when the frame does
+    /// not fit in an `int`.
+    pub fn assign_offsets(&mut self) -> Option<i32> {
+        // the first declared local gets the lowest address
+        let mut offset: i32 = 0;
+        for loc in self.locals.iter_mut().rev() {
+            offset = offset.checked_add(loc.ty.size())?;
+            offset = checked_align_to(offset, loc.ty.align())?;
             loc.offset = -offset;
         }
 
+        // the frame is padded to 16 bytes by the prologue
+        checked_align_to(offset, 16)?;
         self.offset = offset;
 
-        self.offset
+        Some(self.offset)
     }
 }
@@ -1,12 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::ast::Ast;
+use crate::ast::{Ast, CaseLabel, JumpKind};
+use crate::diagnostic::Diagnostic;
 use crate::parser::localscope::LocalScope;
 use crate::parser::precedence::Precedence::*;
 use crate::parser::precedence::{get_precedence, Precedence};
+use crate::tokenizer::literal::{char_value, int_value, string_bytes, IntLiteral};
 use crate::tokenizer::token::Token;
 use crate::tokenizer::tokentype::TokenType;
-use crate::tokenizer::Tokenizer;
+use crate::types::aggregate::{Aggregate, AggregateKind};
+use crate::types::Type;
 
 pub mod localscope;
 mod precedence;
@@ -15,23 +18,53 @@ type ParseFunc = fn(&mut Parser) -> Ast;
 type InfixParseFunc = fn(&mut Parser, Ast) -> Ast;
 
 pub struct Parser {
-    pub tokenizer: Tokenizer,
+    // the preprocessed tokens, ending with the end of file token
+    tokens: Vec<Token>,
+    pos: usize,
     pub infix: HashMap<TokenType, InfixParseFunc>,
     pub prefix: HashMap<TokenType, ParseFunc>,
     curr_token: Option<Token>,
     peek_token: Option<Token>,
     pub scope: LocalScope,
+    pub globals: HashSet<String>,
+    pub strings: Vec<Ast>,
+    // struct and union tags in declaration order, with the block depth
+    // they were declared at
+    pub tags: Vec<(i8, String, Aggregate)>,
+    // how many loops the current statement is in, for break and continue
+    loop_depth: usize,
+    // the case labels of the switches the current statement is in,
+    // innermost last
+    switches: Vec<Vec<CaseLabel>>,
+    // the labels of the current function and the gotos, which may jump
+    // to labels further down
+    labels: HashSet<String>,
+    gotos: Vec<Token>,
+    pub errors: Vec<Diagnostic>,
+    // set by a syntax error until the parser is back at a statement or a
+    // declaration boundary, errors in between are likely caused by it
+    panic_mode: bool,
 }
 
 impl Parser {
-    pub fn new(input: &str) -> Self {
+    pub fn new(tokens: Vec<Token>) -> Self {
         let mut p: Parser = Parser {
-            tokenizer: Tokenizer::new(input),
+            tokens,
+            pos: 0,
             infix: Parser::get_infix(),
             prefix: Parser::get_prefix(),
             curr_token: None,
             peek_token: None,
             scope: LocalScope::new(),
+            globals: HashSet::new(),
+            strings: Vec::new(),
+            tags: Vec::new(),
+            loop_depth: 0,
+            switches: Vec::new(),
+            labels: HashSet::new(),
+            gotos: Vec::new(),
+            errors: Vec::new(),
+            panic_mode: false,
         };
 
         p.next_token();
@@ -43,17 +76,19 @@ impl Parser {
     pub fn get_prefix() -> HashMap<TokenType, ParseFunc> {
         let mut h: HashMap<TokenType, ParseFunc> = HashMap::new();
         h.insert(TokenType::TokenNumber, Parser::parse_number);
-        h.insert(TokenType::TokenIf, Parser::parse_if);
-        h.insert(TokenType::TokenWhile, Parser::parse_while);
-        h.insert(TokenType::TokenFor, Parser::parse_for);
-        h.insert(TokenType::TokenLeftParen, Parser::parse_expr);
-        h.insert(TokenType::TokenLeftBrace, Parser::parse_block);
+        h.insert(TokenType::TokenString, Parser::parse_string);
+        h.insert(TokenType::TokenCharLiteral, Parser::parse_char);
+        h.insert(TokenType::TokenLeftParen, Parser::parse_grouping);
         h.insert(TokenType::TokenMinus, Parser::parse_operator);
         h.insert(TokenType::TokenPlus, Parser::parse_operator);
         h.insert(TokenType::TokenStar, Parser::parse_operator);
         h.insert(TokenType::TokenAddr, Parser::parse_operator);
+        h.insert(TokenType::TokenBang, Parser::parse_operator);
+        h.insert(TokenType::TokenTilde, Parser::parse_operator);
+        h.insert(TokenType::TokenPlusPlus, Parser::parse_prefix_incr);
+        h.insert(TokenType::TokenMinusMinus, Parser::parse_prefix_incr);
         h.insert(TokenType::TokenIdentifier, Parser::parse_identifier);
-        h.insert(TokenType::TokenReturn, Parser::parse_return);
+        h.insert(TokenType::TokenSizeof, Parser::parse_sizeof);
 
         h
     }
@@ -63,7 +98,17 @@ impl Parser {
         h.insert(TokenType::TokenPlus, Parser::parse_arith_expr);
         h.insert(TokenType::TokenMinus, Parser::parse_arith_expr);
         h.insert(TokenType::TokenStar, Parser::parse_arith_expr);
-        h.insert(TokenType::TokenEqual, Parser::parse_arith_expr);
+        h.insert(TokenType::TokenEqual, Parser::parse_assign);
+        h.insert(TokenType::TokenPlusEqual, Parser::parse_assign);
+        h.insert(TokenType::TokenMinusEqual, Parser::parse_assign);
+        h.insert(TokenType::TokenStarEqual, Parser::parse_assign);
+        h.insert(TokenType::TokenSlashEqual, Parser::parse_assign);
+        h.insert(TokenType::TokenPercentEqual, Parser::parse_assign);
+        h.insert(TokenType::TokenAndEqual, Parser::parse_assign);
+        h.insert(TokenType::TokenPipeEqual, Parser::parse_assign);
+        h.insert(TokenType::TokenCaretEqual, Parser::parse_assign);
+        h.insert(TokenType::TokenLessLessEqual, Parser::parse_assign);
+        h.insert(TokenType::TokenGreaterGreaterEqual, Parser::parse_assign);
         h.insert(TokenType::TokenLess, Parser::parse_arith_expr);
         h.insert(TokenType::TokenLessEqual, Parser::parse_arith_expr);
         h.insert(TokenType::TokenEqualEqual, Parser::parse_arith_expr);
@@ -71,14 +116,32 @@ impl Parser {
         h.insert(TokenType::TokenGreater, Parser::parse_arith_expr);
         h.insert(TokenType::TokenGreaterEqual, Parser::parse_arith_expr);
         h.insert(TokenType::TokenSlash, Parser::parse_arith_expr);
+        h.insert(TokenType::TokenPercent, Parser::parse_arith_expr);
+        h.insert(TokenType::TokenLessLess, Parser::parse_arith_expr);
+        h.insert(TokenType::TokenGreaterGreater, Parser::parse_arith_expr);
+        h.insert(TokenType::TokenAddr, Parser::parse_arith_expr);
+        h.insert(TokenType::TokenCaret, Parser::parse_arith_expr);
+        h.insert(TokenType::TokenPipe, Parser::parse_arith_expr);
+        h.insert(TokenType::TokenAndAnd, Parser::parse_arith_expr);
+        h.insert(TokenType::TokenPipePipe, Parser::parse_arith_expr);
+        h.insert(TokenType::TokenComma, Parser::parse_arith_expr);
+        h.insert(TokenType::TokenQuestion, Parser::parse_ternary);
         h.insert(TokenType::TokenLeftParen, Parser::parse_call_expr);
+        h.insert(TokenType::TokenLeftBracket, Parser::parse_index_expr);
+        h.insert(TokenType::TokenDot, Parser::parse_member_expr);
+        h.insert(TokenType::TokenArrow, Parser::parse_member_expr);
+        h.insert(TokenType::TokenPlusPlus, Parser::parse_postfix_incr);
+        h.insert(TokenType::TokenMinusMinus, Parser::parse_postfix_incr);
 
         h
     }
 
+    /// Past the end of the input both tokens stay on the end of file token.
     fn next_token(&mut self) {
         self.curr_token = self.peek_token.clone();
-        self.peek_token = self.tokenizer.next();
+        let last = self.tokens.len() - 1;
+        self.peek_token = Some(self.tokens[self.pos.min(last)].clone());
+        self.pos += 1;
     }
 
     fn curr_token_type(&self) -> &TokenType {
@@ -101,38 +164,167 @@ impl Parser {
             return true;
         }
 
-        eprintln!(
-            "expected next token to be {:?} got {:?}",
-            tok_type,
-            self.peek_token_type()
-        );
+        let tok: Token = self.peek_token.clone().unwrap();
+        let msg = format!("expected {} but got {}", tok_type, Self::describe(&tok));
+        self.error(&tok, msg);
 
         false
     }
 
-    pub fn parse_number(&mut self) -> Ast {
-        Ast::new_literal(self.curr_token.as_ref().unwrap().val.clone())
+    fn error(&mut self, tok: &Token, msg: String) {
+        if self.panic_mode {
+            return;
+        }
+        self.panic_mode = true;
+
+        // the tokenizer already knows what is wrong with an invalid token
+        let msg = match tok.kind {
+            TokenType::TokenError => tok.val.clone(),
+            _ => msg,
+        };
+
+        self.errors.push(Diagnostic::error(tok, msg));
     }
 
-    pub fn parse_identifier(&mut self) -> Ast {
-        let tok: Token = self.curr_token.as_ref().unwrap().clone();
-        let val: String = tok.val.clone();
+    /// Skips the rest of a statement after a syntax error, up to its `;` or
+    /// up to the token before the next statement or the end of the block.
+    /// Braces opened while skipping are skipped along with what they hold,
+    /// so only the `}` of the enclosing block ends the recovery.
+    fn synchronize(&mut self) {
+        self.panic_mode = false;
+        let mut depth = 0;
+
+        while !self.curr_token_type().is(&TokenType::TokenEof) {
+            match self.curr_token_type() {
+                TokenType::TokenLeftBrace => depth += 1,
+                TokenType::TokenRightBrace if depth > 0 => depth -= 1,
+                TokenType::TokenSemicolon if depth == 0 => return,
+                _ => {}
+            }
+
+            if depth > 0 {
+                self.next_token();
+                continue;
+            }
 
-        let id: i8 = self.scope.add_local_if_not_exist(val.as_str());
+            match self.peek_token_type() {
+                TokenType::TokenRightBrace
+                | TokenType::TokenReturn
+                | TokenType::TokenIf
+                | TokenType::TokenFor
+                | TokenType::TokenWhile
+                | TokenType::TokenDo
+                | TokenType::TokenSwitch
+                | TokenType::TokenCase
+                | TokenType::TokenDefault
+                | TokenType::TokenBreak
+                | TokenType::TokenContinue
+                | TokenType::TokenGoto
+                | TokenType::TokenEof => return,
+                t if t.is_type_name() => return,
+                _ => {}
+            }
 
-        if self.peek_token_type().is(&TokenType::TokenEqual) {
             self.next_token();
+        }
+    }
+
+    /// Skips the rest of a top level declaration after a syntax error, up
+    /// to a `;` or `}` followed by what looks like the next declaration.
+    fn synchronize_top_level(&mut self) {
+        self.panic_mode = false;
+
+        while !self.curr_token_type().is(&TokenType::TokenEof) {
+            let at_end = matches!(
+                self.curr_token_type(),
+                TokenType::TokenSemicolon | TokenType::TokenRightBrace
+            );
+            let peek = self.peek_token_type();
+            let at_decl = peek.is_type_name()
+                || peek.is(&TokenType::TokenStatic)
+                || peek.is(&TokenType::TokenEof);
+
+            if at_end && at_decl {
+                return;
+            }
+
             self.next_token();
+        }
+    }
 
-            let assign: Ast = self.parse(PrecedenceAssignment);
-            if self.peek_token_type().is(&TokenType::TokenSemicolon) {
-                self.next_token();
+    /// How the token found instead of the expected one is named in errors.
+    fn describe(tok: &Token) -> String {
+        match tok.kind {
+            TokenType::TokenEof => tok.kind.to_string(),
+            _ => format!("'{}'", tok.val),
+        }
+    }
+
+    /// An integer constant has the first type of `int`, `long` or their
+    /// unsigned variants allowed by its suffix that can hold the value.
+    pub fn parse_number(&mut self) -> Ast {
+        let tok: Token = self.curr_token.clone().unwrap();
+        let lit: IntLiteral = match int_value(&tok) {
+            Ok(lit) => lit,
+            Err(msg) => {
+                self.errors.push(Diagnostic::error(&tok, msg));
+                return Ast::new_typed_literal("0".to_string(), Type::Int);
             }
+        };
 
-            Ast::new_variable_assign(id, tok, val, assign)
-        } else {
-            Ast::new_variable(id, tok, val)
+        // octal and hexadecimal constants may also take the unsigned type of
+        // each size before moving on to the next size
+        let ty = match (lit.unsigned, lit.long) {
+            (false, false) if lit.value <= i32::MAX as u64 => Type::Int,
+            (u, false) if (u || !lit.decimal) && lit.value <= u32::MAX as u64 => Type::UInt,
+            (false, _) if lit.value <= i64::MAX as u64 => Type::Long,
+            _ => Type::ULong,
+        };
+
+        // values above i64::MAX are kept in two's complement
+        Ast::new_typed_literal((lit.value as i64).to_string(), ty)
+    }
+
+    /// Adjacent string literals are concatenated into a single one.
+    pub fn parse_string(&mut self) -> Ast {
+        let tok: Token = self.curr_token.as_ref().unwrap().clone();
+        let mut bytes: Vec<u8> = string_bytes(&tok);
+
+        while self.peek_token_type().is(&TokenType::TokenString) {
+            self.next_token();
+            bytes.append(&mut string_bytes(self.curr_token.as_ref().unwrap()));
+        }
+
+        let label: String = format!(".L.str.{}", self.strings.len());
+        self.strings
+            .push(Ast::new_string_data(tok, label.clone(), &bytes));
+
+        Ast::new_string_literal(label, bytes)
+    }
+
+    pub fn parse_char(&mut self) -> Ast {
+        let tok: &Token = self.curr_token.as_ref().unwrap();
+        Ast::new_char_literal(char_value(tok))
+    }
+
+    pub fn parse_identifier(&mut self) -> Ast {
+        let tok: Token = self.curr_token.as_ref().unwrap().clone();
+        let val: String = tok.val.clone();
+
+        // a callee name is not a local, the call itself is parsed by parse_call_expr
+        if self.peek_token_type().is(&TokenType::TokenLeftParen) {
+            return Ast::new_func_call(tok, val, Vec::new());
+        }
+
+        if let Some(id) = self.scope.resolve_local(val.as_str()) {
+            return Ast::new_variable(id, tok, val);
+        }
+
+        if !self.globals.contains(&val) {
+            self.error(&tok, format!("undeclared identifier '{}'", val));
         }
+
+        Ast::new_global_variable(tok, val)
     }
 
     pub fn parse_expr(&mut self) -> Ast {
@@ -145,8 +337,28 @@ impl Parser {
         return ast;
     }
 
-    pub fn parse_block(&mut self) -> Ast {
+    /// Parses a parenthesized expression or, when a type name follows the
+    /// parenthesis, a cast `(type)expr`.
+    fn parse_grouping(&mut self) -> Ast {
+        if !self.peek_token_type().is_type_name() {
+            return self.parse_expr();
+        }
+
+        let tok: Token = self.curr_token.clone().unwrap();
+        self.next_token(); // consume left parenthesis
+
+        let base: Type = self.parse_typespec();
+        let ty: Type = self.parse_pointers(base);
+        self.expect_peek(&TokenType::TokenRightParen);
         self.next_token();
+
+        let expr: Ast = self.parse(PrecedenceUnary);
+        Ast::new_cast(tok, expr, ty)
+    }
+
+    pub fn parse_block(&mut self) -> Ast {
+        self.next_token(); // consume left brace
+        self.scope.begin_scope();
         let mut stmts = Vec::new();
 
         while !self.curr_token_type().is(&TokenType::TokenRightBrace)
@@ -160,13 +372,22 @@ impl Parser {
             let ast: Ast = self.parse_stmt();
             stmts.push(ast);
 
+            if self.panic_mode {
+                self.synchronize();
+            }
+
             self.next_token();
         }
 
-        if self.curr_token_type().is(&TokenType::TokenRightBrace) {
-            self.next_token();
+        if self.curr_token_type().is(&TokenType::TokenEof) {
+            let tok: Token = self.curr_token.clone().unwrap();
+            self.error(&tok, "expected '}' but got end of file".to_string());
         }
 
+        self.scope.end_scope();
+        let depth = self.scope.scope_depth;
+        self.tags.retain(|(d, _, _)| *d <= depth);
+
         Ast::new_block(stmts)
     }
 
@@ -189,40 +410,90 @@ impl Parser {
         Ast::new_unary(token, tok_val, right)
     }
 
-    pub fn parse_for_arguments(&mut self) -> (Option<Ast>, Option<Ast>, Option<Ast>) {
-        let init: Option<Ast> = if !self.peek_token_type().is(&TokenType::TokenSemicolon) {
-            self.next_token();
-            Some(self.parse(PrecedenceAssignment))
-        } else {
-            self.next_token();
-            None
+    /// `++x` is parsed as `x += 1` and `--x` as `x -= 1`.
+    pub fn parse_prefix_incr(&mut self) -> Ast {
+        let mut token: Token = self.curr_token.as_ref().unwrap().clone();
+        let kind = match token.kind {
+            TokenType::TokenPlusPlus => TokenType::TokenPlusEqual,
+            _ => TokenType::TokenMinusEqual,
         };
+        token.update_kind(kind);
 
-        let cond: Option<Ast> = if !self.peek_token_type().is(&TokenType::TokenSemicolon) {
-            self.next_token();
-            let r = Some(self.parse(PrecedenceAssignment));
-            self.next_token();
-            r
-        } else {
-            self.next_token();
-            None
-        };
+        self.next_token();
 
-        let incr: Option<Ast> = if !self.peek_token_type().is(&TokenType::TokenRightParen) {
-            self.next_token();
-            let r = Some(self.parse(PrecedenceNone));
+        let operand: Ast = self.parse(PrecedenceUnary);
+        Ast::new_assign(token, operand, Ast::new_literal("1".to_string()))
+    }
+
+    /// `x++` and `x--`, a unary node whose value is that of `x` before it
+    /// is incremented or decremented.
+    pub fn parse_postfix_incr(&mut self, left: Ast) -> Ast {
+        let tok: Token = self.curr_token.as_ref().unwrap().clone();
+        let operator: String = tok.val.clone();
+
+        Ast::new_unary(tok, operator, left)
+    }
+
+    /// `sizeof(type)` is folded here, the operand of `sizeof expr` is kept
+    /// until the type checker knows its type.
+    pub fn parse_sizeof(&mut self) -> Ast {
+        let tok: Token = self.curr_token.as_ref().unwrap().clone();
+        let tok_val: String = tok.val.clone();
+
+        if self.peek_token_type().is(&TokenType::TokenLeftParen) {
             self.next_token();
-            r
-        } else {
+
+            if !self.peek_token_type().is_type_name() {
+                let expr: Ast = self.parse_expr();
+                return Ast::new_unary(tok, tok_val, expr);
+            }
+
             self.next_token();
-            None
-        };
+            let ty: Type = self.parse_typespec();
+            let ty: Type = self.parse_pointers(ty);
+            let ty: Type = self.parse_array_dimensions(ty);
+            self.expect_peek(&TokenType::TokenRightParen);
+
+            if ty.is_incomplete() {
+                let msg = format!(
+                    "invalid application of 'sizeof' to an incomplete type '{}'",
+                    ty
+                );
+                self.errors.push(Diagnostic::error(&tok, msg));
+            }
 
-        self.next_token(); // consume right parenthesis
+            return Ast::new_typed_literal(ty.size().to_string(), Type::ULong);
+        }
+
+        self.next_token();
+
+        let right: Ast = self.parse(PrecedenceUnary);
+        Ast::new_unary(tok, tok_val, right)
+    }
+
+    pub fn parse_for_arguments(&mut self) -> (Option<Ast>, Option<Ast>, Option<Ast>) {
+        self.next_token(); // consume left parenthesis
+
+        let init: Option<Ast> = self.parse_for_clause(&TokenType::TokenSemicolon);
+        let cond: Option<Ast> = self.parse_for_clause(&TokenType::TokenSemicolon);
+        let incr: Option<Ast> = self.parse_for_clause(&TokenType::TokenRightParen);
 
         (init, cond, incr)
     }
 
+    fn parse_for_clause(&mut self, terminator: &TokenType) -> Option<Ast> {
+        if self.curr_token_type().is(terminator) {
+            self.next_token();
+            return None;
+        }
+
+        let clause: Ast = self.parse(PrecedenceNone);
+        self.expect_peek(terminator);
+        self.next_token();
+
+        Some(clause)
+    }
+
     pub fn parse_if(&mut self) -> Ast {
         let tok: Token = self.curr_token.clone().unwrap();
         self.next_token();
@@ -233,6 +504,7 @@ impl Parser {
         let then: Ast = self.parse_stmt();
 
         let alt: Option<Ast> = if self.peek_token_type().is(&TokenType::TokenElse) {
+            self.next_token(); // consume else
             self.next_token();
             Some(self.parse_stmt())
         } else {
@@ -248,7 +520,7 @@ impl Parser {
 
         let (init, cond, incr) = self.parse_for_arguments();
 
-        let then = self.parse_stmt();
+        let then = self.parse_loop_body();
 
         Ast::new_for(tok, init, cond, incr, then)
     }
@@ -261,19 +533,207 @@ impl Parser {
 
         self.next_token(); // consume right parenthesis
 
-        let then: Ast = self.parse_stmt();
+        let then: Ast = self.parse_loop_body();
 
         Ast::new_while(tok, Some(cond), then)
     }
 
+    fn parse_loop_body(&mut self) -> Ast {
+        self.loop_depth += 1;
+        let body: Ast = self.parse_stmt();
+        self.loop_depth -= 1;
+
+        body
+    }
+
+    pub fn parse_loop_jump(&mut self) -> Ast {
+        let tok: Token = self.curr_token.clone().unwrap();
+        let (kind, msg, outside) = match tok.kind {
+            TokenType::TokenBreak => (
+                JumpKind::Break,
+                "'break' statement not in loop or switch statement",
+                self.loop_depth == 0 && self.switches.is_empty(),
+            ),
+            _ => (
+                JumpKind::Continue,
+                "'continue' statement not in loop statement",
+                self.loop_depth == 0,
+            ),
+        };
+
+        if outside {
+            self.errors.push(Diagnostic::error(&tok, msg.to_string()));
+        }
+        self.expect_peek(&TokenType::TokenSemicolon);
+
+        Ast::new_jump(tok, kind)
+    }
+
+    pub fn parse_switch(&mut self) -> Ast {
+        let tok: Token = self.curr_token.clone().unwrap();
+        self.next_token();
+
+        let cond: Ast = self.parse_expr();
+        self.next_token(); // consume right parenthesis
+
+        self.switches.push(Vec::new());
+        let body: Ast = self.parse_stmt();
+        let cases: Vec<CaseLabel> = self.switches.pop().unwrap_or_default();
+
+        Ast::new_switch(tok, cond, body, cases)
+    }
+
+    /// `case expr: stmt` or `default: stmt`, the label is added to the
+    /// innermost switch.
+    pub fn parse_case(&mut self) -> Ast {
+        let tok: Token = self.curr_token.clone().unwrap();
+
+        let expr: Option<Box<Ast>> = match tok.kind {
+            TokenType::TokenCase => {
+                self.next_token();
+                Some(Box::new(self.parse(PrecedenceTernary)))
+            }
+            _ => None,
+        };
+        self.expect_peek(&TokenType::TokenColon);
+        self.next_token();
+
+        let msg = match self.switches.last() {
+            None => Some(format!("'{}' statement not in switch statement", tok.val)),
+            Some(cases) if expr.is_none() && cases.iter().any(|c| c.expr.is_none()) => {
+                Some("multiple default labels in one switch".to_string())
+            }
+            Some(_) => None,
+        };
+        if let Some(msg) = msg {
+            self.errors.push(Diagnostic::error(&tok, msg));
+        }
+
+        let label = CaseLabel {
+            token: tok,
+            expr,
+            value: 0,
+        };
+        let index = match self.switches.last_mut() {
+            Some(cases) => {
+                cases.push(label);
+                cases.len() - 1
+            }
+            None => 0,
+        };
+
+        let stmt: Ast = self.parse_stmt();
+        Ast::new_case(index, stmt)
+    }
+
+    pub fn parse_goto(&mut self) -> Ast {
+        let tok: Token = self.curr_token.clone().unwrap();
+        if !self.expect_peek(&TokenType::TokenIdentifier) {
+            return Ast::new_block(Vec::new());
+        }
+
+        let label: Token = self.curr_token.clone().unwrap();
+        self.expect_peek(&TokenType::TokenSemicolon);
+
+        let kind = JumpKind::Goto(label.val.clone());
+        self.gotos.push(label);
+        Ast::new_jump(tok, kind)
+    }
+
+    /// `name: stmt`, the current token is the label name.
+    pub fn parse_label(&mut self) -> Ast {
+        let tok: Token = self.curr_token.clone().unwrap();
+        let name: String = tok.val.clone();
+
+        if !self.labels.insert(name.clone()) {
+            let msg = format!("redefinition of label '{}'", name);
+            self.errors.push(Diagnostic::error(&tok, msg));
+        }
+
+        self.next_token(); // consume colon
+        self.next_token();
+
+        let stmt: Ast = self.parse_stmt();
+        Ast::new_label(name, stmt)
+    }
+
     pub fn parse_do_while(&mut self) -> Ast {
         let tok: Token = self.curr_token.as_ref().unwrap().clone();
-        let then: Ast = self.parse_stmt();
-        Ast::new_do_while(tok, then)
+        self.next_token(); // consume do
+
+        let then: Ast = self.parse_loop_body();
+
+        self.expect_peek(&TokenType::TokenWhile);
+        self.expect_peek(&TokenType::TokenLeftParen);
+        let cond: Ast = self.parse_expr();
+        self.expect_peek(&TokenType::TokenSemicolon);
+
+        Ast::new_do_while(tok, cond, then)
     }
 
     pub fn parse_call_expr(&mut self, left: Ast) -> Ast {
-        Ast::new_func_call()
+        let (tok, name) = match left {
+            Ast::FunctionCall(callee) if callee.args.is_empty() => (callee.token, callee.name),
+            _ => {
+                let tok: Token = self.curr_token.clone().unwrap();
+                self.error(&tok, "called object is not a function".to_string());
+                return left;
+            }
+        };
+
+        let mut args = Vec::new();
+        while !self.peek_token_type().is(&TokenType::TokenRightParen)
+            && !self.peek_token_type().is(&TokenType::TokenEof)
+        {
+            if !args.is_empty() {
+                self.expect_peek(&TokenType::TokenComma);
+            }
+
+            self.next_token();
+            args.push(self.parse(PrecedenceAssignment));
+        }
+
+        self.expect_peek(&TokenType::TokenRightParen);
+
+        Ast::new_func_call(tok, name, args)
+    }
+
+    /// `a[i]` is sugar for `*(a + i)`.
+    pub fn parse_index_expr(&mut self, left: Ast) -> Ast {
+        let tok: Token = self.curr_token.as_ref().unwrap().clone();
+
+        self.next_token(); // consume left bracket
+        let index: Ast = self.parse(PrecedenceNone);
+        self.expect_peek(&TokenType::TokenRightBracket);
+
+        let mut plus: Token = tok.clone();
+        plus.update_kind(TokenType::TokenPlus);
+        let mut deref: Token = tok;
+        deref.update_kind(TokenType::TokenDeref);
+
+        let addr: Ast = Ast::new_arith_exp(plus, "+".to_string(), left, index);
+        Ast::new_unary(deref, "*".to_string(), addr)
+    }
+
+    /// `p->x` is sugar for `(*p).x`.
+    pub fn parse_member_expr(&mut self, left: Ast) -> Ast {
+        let tok: Token = self.curr_token.as_ref().unwrap().clone();
+
+        let left: Ast = match tok.kind {
+            TokenType::TokenArrow => {
+                let mut deref: Token = tok.clone();
+                deref.update_kind(TokenType::TokenDeref);
+                Ast::new_unary(deref, "*".to_string(), left)
+            }
+            _ => left,
+        };
+
+        if !self.expect_peek(&TokenType::TokenIdentifier) {
+            return left;
+        }
+        let name: String = self.curr_token.as_ref().unwrap().val.clone();
+
+        Ast::new_member_access(tok, left, name)
     }
 
     pub fn parse_arith_expr(&mut self, left: Ast) -> Ast {
@@ -284,41 +744,559 @@ impl Parser {
 
         self.next_token();
 
-        let right: Ast = self.parse(precedence);
+        // binary operators are left associative, a - b + c is (a - b) + c
+        let right: Ast = self.parse(precedence.next());
 
         Ast::new_arith_exp(tok, operator, left, right)
     }
 
+    /// `cond ? then : alt` is right associative, `a ? b : c ? d : e` is
+    /// `a ? b : (c ? d : e)`. The middle operand may be any expression.
+    pub fn parse_ternary(&mut self, cond: Ast) -> Ast {
+        let tok: Token = self.curr_token.as_ref().unwrap().clone();
+
+        self.next_token();
+        let then: Ast = self.parse(PrecedenceNone);
+
+        self.expect_peek(&TokenType::TokenColon);
+        self.next_token();
+        let alt: Ast = self.parse(PrecedenceTernary);
+
+        Ast::new_ternary(tok, cond, then, alt)
+    }
+
+    pub fn parse_assign(&mut self, left: Ast) -> Ast {
+        let tok: Token = self.curr_token.as_ref().unwrap().clone();
+
+        self.next_token();
+
+        // assignment is right associative, a = b = c is a = (b = c)
+        let right: Ast = self.parse(PrecedenceAssignment);
+
+        Ast::new_assign(tok, left, right)
+    }
+
     pub fn parse_program(&mut self) -> Ast {
-        let mut stmts = Vec::new();
+        let mut decls = Vec::new();
         loop {
             if self.curr_token_type().is(&TokenType::TokenEof) {
                 break;
             }
 
-            let stmt: Ast = self.parse_stmt();
-            stmts.push(stmt);
+            decls.append(&mut self.parse_top_level());
+
+            if self.panic_mode {
+                self.synchronize_top_level();
+            }
 
             self.next_token();
         }
 
+        decls.append(&mut self.strings);
+
+        Ast::new_program(decls)
+    }
+
+    /// Parses a function definition or a declaration of global variables,
+    /// which may declare several of them.
+    fn parse_top_level(&mut self) -> Vec<Ast> {
+        let tok: Token = self.curr_token.clone().unwrap();
+
+        let is_static = tok.is(TokenType::TokenStatic);
+        if is_static {
+            self.next_token();
+        }
+
+        let base: Type = self.parse_typespec();
+
+        // a struct or union declared on its own
+        if self.peek_token_type().is(&TokenType::TokenSemicolon) {
+            self.next_token();
+            return Vec::new();
+        }
+
+        let ret_ty: Type = self.parse_pointers(base.clone());
+        if !self.expect_peek(&TokenType::TokenIdentifier) {
+            return Vec::new();
+        }
+
+        if self.peek_token_type().is(&TokenType::TokenLeftParen) {
+            return self
+                .parse_function(tok, ret_ty, is_static)
+                .into_iter()
+                .collect();
+        }
+
+        self.parse_global_vars(base, ret_ty, is_static)
+    }
+
+    /// Parses the rest of a function definition, the current token is its name.
+    /// Without a body nothing is returned and the caller skips to the next
+    /// top level declaration.
+    pub fn parse_function(&mut self, tok: Token, ret_ty: Type, is_static: bool) -> Option<Ast> {
+        self.scope = LocalScope::new();
+        self.labels.clear();
+
+        let name_tok: Token = self.curr_token.clone().unwrap();
+        let name: String = name_tok.val.clone();
+        if let Type::Struct(_) = ret_ty {
+            let msg = format!("returning {} by value is not supported", ret_ty);
+            self.errors.push(Diagnostic::error(&name_tok, msg));
+        }
+
+        self.expect_peek(&TokenType::TokenLeftParen);
+        let params: Vec<usize> = self.parse_params();
+
+        let param_types: Vec<Type> = params
+            .iter()
+            .map(|id| self.scope.type_by_id(*id).unwrap().clone())
+            .collect();
+        let ty = Type::func_type(ret_ty, param_types);
+
+        if !self.expect_peek(&TokenType::TokenLeftBrace) {
+            return None;
+        }
+        let body: Ast = self.parse_block();
+
+        for label in std::mem::take(&mut self.gotos) {
+            if !self.labels.contains(&label.val) {
+                let msg = format!("use of undeclared label '{}'", label.val);
+                self.errors.push(Diagnostic::error(&label, msg));
+            }
+        }
+
         // assign asm stack offset
-        self.scope.assign_offsets();
+        if self.scope.assign_offsets().is_none() {
+            let msg = format!("stack frame of '{}' is too large", name);
+            self.errors.push(Diagnostic::error(&name_tok, msg));
+        }
+        let scope = std::mem::replace(&mut self.scope, LocalScope::new());
 
-        Ast::new_program(stmts)
+        Some(Ast::new_function(
+            tok, name, ty, is_static, params, body, scope,
+        ))
     }
 
-    pub fn parse_stmt(&mut self) -> Ast {
-        // TODO: check for other statement beginning tokens
-        self.parse_expr_stmt()
+    /// Parses the rest of a global declaration, the current token is the
+    /// name of the first variable and `ty` its type without array suffixes.
+    fn parse_global_vars(&mut self, base: Type, ty: Type, is_static: bool) -> Vec<Ast> {
+        let mut globals = Vec::new();
+        let mut ty: Type = ty;
+
+        loop {
+            let name_tok: Token = self.curr_token.clone().unwrap();
+            let name: String = name_tok.val.clone();
+            let var_ty: Type = self.parse_array_dimensions(ty);
+
+            if var_ty.is_incomplete() {
+                let msg = format!("variable '{}' has incomplete type '{}'", name, var_ty);
+                self.error(&name_tok, msg);
+            }
+            if !self.globals.insert(name.clone()) {
+                self.error(&name_tok, format!("redefinition of '{}'", name));
+            }
+
+            let init: Vec<Ast> = if self.peek_token_type().is(&TokenType::TokenEqual) {
+                self.next_token(); // consume equal
+                self.next_token();
+                self.parse_initializer()
+            } else {
+                Vec::new()
+            };
+
+            globals.push(Ast::new_global_var(name_tok, name, var_ty, is_static, init));
+
+            if !self.peek_token_type().is(&TokenType::TokenComma) {
+                break;
+            }
+            self.next_token();
+
+            ty = self.parse_pointers(base.clone());
+            if !self.expect_peek(&TokenType::TokenIdentifier) {
+                break;
+            }
+        }
+
+        self.expect_peek(&TokenType::TokenSemicolon);
+
+        globals
     }
 
-    pub fn parse_expr_stmt(&mut self) -> Ast {
-        let ast: Ast = self.parse(PrecedenceAssignment);
+    /// Parses `expr` or a brace enclosed list of initializers. Nested lists
+    /// are flattened, the elements are stored in declaration order.
+    fn parse_initializer(&mut self) -> Vec<Ast> {
+        if !self.curr_token_type().is(&TokenType::TokenLeftBrace) {
+            return vec![self.parse(PrecedenceAssignment)];
+        }
+
+        let mut inits = Vec::new();
+        while !self.peek_token_type().is(&TokenType::TokenRightBrace)
+            && !self.peek_token_type().is(&TokenType::TokenEof)
+        {
+            self.next_token();
+            inits.append(&mut self.parse_initializer());
+
+            if !self.peek_token_type().is(&TokenType::TokenComma) {
+                break;
+            }
+            self.next_token();
+        }
+
+        self.expect_peek(&TokenType::TokenRightBrace);
+
+        inits
+    }
+
+    fn parse_params(&mut self) -> Vec<usize> {
+        let mut params = Vec::new();
+
+        while !self.peek_token_type().is(&TokenType::TokenRightParen)
+            && !self.peek_token_type().is(&TokenType::TokenEof)
+        {
+            if !params.is_empty() {
+                self.expect_peek(&TokenType::TokenComma);
+            }
+
+            self.next_token();
+            let ty: Type = self.parse_typespec();
+            let ty: Type = self.parse_pointers(ty);
+
+            self.expect_peek(&TokenType::TokenIdentifier);
+
+            let name_tok: Token = self.curr_token.clone().unwrap();
+            let name: String = name_tok.val.clone();
+            if self.scope.contains(name.as_str()).0 {
+                self.error(&name_tok, format!("redefinition of '{}'", name));
+            }
+            if let Type::Struct(_) = ty {
+                let msg = format!("passing {} by value is not supported", ty);
+                self.errors.push(Diagnostic::error(&name_tok, msg));
+            }
+            params.push(self.scope.add_local(name.as_str(), ty));
+        }
+
+        self.expect_peek(&TokenType::TokenRightParen);
+
+        params
+    }
+
+    /// Parses `int a, *b = &a, c[10];` registering each declarator as a
+    /// local of the current block. Only initialized declarators produce code.
+    pub fn parse_declaration(&mut self) -> Ast {
+        let tok: Token = self.curr_token.clone().unwrap();
+        let base: Type = self.parse_typespec();
+
+        let mut inits = Vec::new();
         if self.peek_token_type().is(&TokenType::TokenSemicolon) {
             self.next_token();
+            return Ast::new_declaration(tok, inits);
+        }
+
+        loop {
+            let ty: Type = self.parse_pointers(base.clone());
+            if !self.expect_peek(&TokenType::TokenIdentifier) {
+                break;
+            }
+
+            let name_tok: Token = self.curr_token.clone().unwrap();
+            let name: String = name_tok.val.clone();
+            let ty: Type = self.parse_array_dimensions(ty);
+
+            if ty.is_incomplete() {
+                let msg = format!("variable '{}' has incomplete type '{}'", name, ty);
+                self.error(&name_tok, msg);
+            }
+            if self.scope.contains(name.as_str()).0 {
+                self.error(&name_tok, format!("redefinition of '{}'", name));
+            }
+            let id: usize = self.scope.add_local(name.as_str(), ty);
+
+            if self.peek_token_type().is(&TokenType::TokenEqual) {
+                self.next_token();
+                let eq_tok: Token = self.curr_token.clone().unwrap();
+                self.next_token();
+
+                let init: Ast = self.parse(PrecedenceAssignment);
+                let var: Ast = Ast::new_variable(id, name_tok, name);
+                inits.push(Ast::new_assign(eq_tok, var, init));
+            }
+
+            if !self.peek_token_type().is(&TokenType::TokenComma) {
+                break;
+            }
+            self.next_token();
+        }
+
+        self.expect_peek(&TokenType::TokenSemicolon);
+
+        Ast::new_declaration(tok, inits)
+    }
+
+    /// Parses the `[N]` suffixes of a declarator, `int a[2][3]` is an array
+    /// of two arrays of three ints.
+    fn parse_array_dimensions(&mut self, base: Type) -> Type {
+        if !self.peek_token_type().is(&TokenType::TokenLeftBracket) {
+            return base;
+        }
+
+        self.next_token(); // consume left bracket
+        let dim: Option<Token> = match self.expect_peek(&TokenType::TokenNumber) {
+            true => self.curr_token.clone(),
+            false => None,
+        };
+        self.expect_peek(&TokenType::TokenRightBracket);
+
+        let elem: Type = self.parse_array_dimensions(base);
+        let len: usize = match dim {
+            Some(dim) => self.array_len(&dim, &elem),
+            None => 0,
+        };
+        Type::array_of(elem, len)
+    }
+
+    /// The length given by the dimension token `dim` of an array of
+    /// `elem`. The size of the array in bytes has to fit in an `int`, the
+    /// length is 0 after reporting when it does not.
+    fn array_len(&mut self, dim: &Token, elem: &Type) -> usize {
+        let len: u64 = match int_value(dim) {
+            Ok(lit) => lit.value,
+            Err(_) => {
+                let msg = "invalid array size".to_string();
+                self.errors.push(Diagnostic::error(dim, msg));
+                return 0;
+            }
+        };
+
+        match len.checked_mul(elem.size() as u64) {
+            Some(size) if size <= i32::MAX as u64 => len as usize,
+            _ => {
+                let msg = "array is too large".to_string();
+                self.errors.push(Diagnostic::error(dim, msg));
+                0
+            }
+        }
+    }
+
+    /// Parses the base type keyword at the current token.
+    fn parse_typespec(&mut self) -> Type {
+        match self.curr_token_type() {
+            TokenType::TokenStruct => self.parse_aggregate(AggregateKind::Struct),
+            TokenType::TokenUnion => self.parse_aggregate(AggregateKind::Union),
+            t if t.is_type_name() => self.parse_integer_type(),
+            _ => {
+                let tok: Token = self.curr_token.clone().unwrap();
+                let msg = format!("expected a type name but got {}", Self::describe(&tok));
+                self.error(&tok, msg);
+                Type::Int
+            }
+        }
+    }
+
+    /// Parses the keywords of an integer type written in any order, like
+    /// `unsigned long int` or `short`, up to the last one.
+    fn parse_integer_type(&mut self) -> Type {
+        let (mut chars, mut shorts, mut ints, mut longs) = (0, 0, 0, 0);
+        let (mut signs, mut unsigned) = (0, false);
+        let mut valid = true;
+
+        loop {
+            match self.curr_token_type() {
+                TokenType::TokenChar => chars += 1,
+                TokenType::TokenShort => shorts += 1,
+                TokenType::TokenInt => ints += 1,
+                TokenType::TokenLong => longs += 1,
+                TokenType::TokenUnsigned => (signs, unsigned) = (signs + 1, true),
+                _ => signs += 1,
+            }
+
+            // only `long` may be repeated, and only once
+            let combines = ints <= 1
+                && matches!(
+                    (chars, shorts, ints, longs),
+                    (1, 0, 0, 0) | (0, 0 | 1, _, 0) | (0, 0, _, 1 | 2)
+                );
+            if valid && (!combines || signs > 1) {
+                let tok: Token = self.curr_token.clone().unwrap();
+                self.error(&tok, "invalid combination of type specifiers".to_string());
+                valid = false;
+            }
+
+            match self.peek_token_type() {
+                TokenType::TokenStruct | TokenType::TokenUnion => break,
+                t if t.is_type_name() => self.next_token(),
+                _ => break,
+            }
+        }
+
+        let ty = match (chars, shorts, longs) {
+            (0, 0, 0) => Type::Int,
+            (_, 0, 0) => Type::Char,
+            (0, _, 0) => Type::Short,
+            _ => Type::Long,
+        };
+
+        match unsigned {
+            true => ty.to_unsigned(),
+            false => ty,
+        }
+    }
+
+    /// Parses `struct tag { members }`, `struct { members }` or a reference
+    /// to `struct tag`, the current token is the keyword. A tag that is not
+    /// declared yet is declared as an incomplete type.
+    fn parse_aggregate(&mut self, kind: AggregateKind) -> Type {
+        let tok: Token = self.curr_token.clone().unwrap();
+
+        let tag: Option<String> = match self.peek_token_type() {
+            TokenType::TokenIdentifier => {
+                self.next_token();
+                Some(self.curr_token.as_ref().unwrap().val.clone())
+            }
+            _ => None,
+        };
+
+        if !self.peek_token_type().is(&TokenType::TokenLeftBrace) {
+            let tag: String = match tag {
+                Some(tag) => tag,
+                None => {
+                    self.error(&tok, format!("expected a tag or '{{' after '{}'", tok.val));
+                    return Type::Int;
+                }
+            };
+
+            if let Some((_, _, agg)) = self.find_tag(tag.as_str()) {
+                let agg: Aggregate = agg.clone();
+                if agg.kind() != kind {
+                    self.error(&tok, format!("use of '{}' with a different tag kind", agg));
+                }
+                return Type::Struct(agg);
+            }
+
+            let agg = Aggregate::new(kind, Some(tag.clone()));
+            self.declare_tag(tag, agg.clone());
+            return Type::Struct(agg);
+        }
+
+        // a definition completes a forward declaration of the same block
+        let depth = self.scope.scope_depth;
+        let declared: Option<Aggregate> = tag
+            .as_ref()
+            .and_then(|tag| self.find_tag(tag.as_str()))
+            .filter(|(d, _, _)| *d == depth)
+            .map(|(_, _, agg)| agg.clone());
+
+        let agg: Aggregate = match declared {
+            Some(agg) if agg.kind() == kind && !agg.is_complete() => agg,
+            Some(agg) => {
+                self.error(&tok, format!("redefinition of '{}'", agg));
+                Aggregate::new(kind, tag)
+            }
+            None => {
+                let agg = Aggregate::new(kind, tag.clone());
+                if let Some(tag) = tag {
+                    self.declare_tag(tag, agg.clone());
+                }
+                agg
+            }
+        };
+
+        self.next_token(); // consume left brace
+        let members: Vec<(String, Type)> = self.parse_members();
+        if !agg.define(members) {
+            self.errors
+                .push(Diagnostic::error(&tok, format!("{} is too large", agg)));
+        }
+
+        Type::Struct(agg)
+    }
+
+    /// Parses member declarations up to the closing brace.
+    fn parse_members(&mut self) -> Vec<(String, Type)> {
+        let mut members: Vec<(String, Type)> = Vec::new();
+
+        while !self.peek_token_type().is(&TokenType::TokenRightBrace)
+            && !self.peek_token_type().is(&TokenType::TokenEof)
+        {
+            self.next_token();
+            let base: Type = self.parse_typespec();
+
+            loop {
+                let ty: Type = self.parse_pointers(base.clone());
+                if !self.expect_peek(&TokenType::TokenIdentifier) {
+                    break;
+                }
+
+                let name_tok: Token = self.curr_token.clone().unwrap();
+                let ty: Type = self.parse_array_dimensions(ty);
+
+                if ty.is_incomplete() {
+                    let msg = format!("field '{}' has incomplete type '{}'", name_tok.val, ty);
+                    self.error(&name_tok, msg);
+                }
+                if members.iter().any(|(name, _)| *name == name_tok.val) {
+                    self.error(&name_tok, format!("duplicate member '{}'", name_tok.val));
+                }
+                members.push((name_tok.val, ty));
+
+                if !self.peek_token_type().is(&TokenType::TokenComma) {
+                    break;
+                }
+                self.next_token();
+            }
+
+            self.expect_peek(&TokenType::TokenSemicolon);
         }
 
+        self.expect_peek(&TokenType::TokenRightBrace);
+
+        members
+    }
+
+    fn declare_tag(&mut self, tag: String, agg: Aggregate) {
+        self.tags.push((self.scope.scope_depth, tag, agg));
+    }
+
+    /// Looks for a tag in every enclosing block, innermost first.
+    fn find_tag(&self, tag: &str) -> Option<&(i8, String, Aggregate)> {
+        self.tags.iter().rev().find(|(_, name, _)| name == tag)
+    }
+
+    /// Wraps `base` in a pointer type for each `*` that follows.
+    fn parse_pointers(&mut self, base: Type) -> Type {
+        let mut ty = base;
+        while self.peek_token_type().is(&TokenType::TokenStar) {
+            self.next_token();
+            ty = Type::pointer_to(ty);
+        }
+
+        ty
+    }
+
+    pub fn parse_stmt(&mut self) -> Ast {
+        match self.curr_token_type() {
+            TokenType::TokenReturn => self.parse_return(),
+            TokenType::TokenIf => self.parse_if(),
+            TokenType::TokenFor => self.parse_for(),
+            TokenType::TokenWhile => self.parse_while(),
+            TokenType::TokenDo => self.parse_do_while(),
+            TokenType::TokenSwitch => self.parse_switch(),
+            TokenType::TokenCase | TokenType::TokenDefault => self.parse_case(),
+            TokenType::TokenBreak | TokenType::TokenContinue => self.parse_loop_jump(),
+            TokenType::TokenGoto => self.parse_goto(),
+            TokenType::TokenIdentifier if self.peek_token_type().is(&TokenType::TokenColon) => {
+                self.parse_label()
+            }
+            TokenType::TokenLeftBrace => self.parse_block(),
+            TokenType::TokenSemicolon => Ast::new_block(Vec::new()),
+            t if t.is_type_name() => self.parse_declaration(),
+            _ => self.parse_expr_stmt(),
+        }
+    }
+
+    pub fn parse_expr_stmt(&mut self) -> Ast {
+        let ast: Ast = self.parse(PrecedenceNone);
+        self.expect_peek(&TokenType::TokenSemicolon);
+
         ast
     }
 
@@ -327,7 +1305,7 @@ impl Parser {
 
         self.next_token();
 
-        let value: Ast = self.parse(PrecedenceAssignment);
+        let value: Ast = self.parse(PrecedenceNone);
 
         self.expect_peek(&TokenType::TokenSemicolon);
 
@@ -335,20 +1313,16 @@ impl Parser {
     }
 
     pub fn parse(&mut self, precedence: Precedence) -> Ast {
-        let cur_tok_type: &TokenType = self.curr_token_type();
-
-        let prefix_rule = self.prefix.get(&cur_tok_type);
-        match prefix_rule {
+        let prefix_rule: ParseFunc = match self.prefix.get(self.curr_token_type()) {
+            Some(rule) => *rule,
             None => {
-                eprintln!(
-                    "no prefix parse func {:#?}, {:#?}",
-                    self.curr_token, self.peek_token,
-                );
+                let tok: Token = self.curr_token.clone().unwrap();
+                let msg = format!("expected an expression but got {}", Self::describe(&tok));
+                self.error(&tok, msg);
+                return Ast::new_literal("0".to_string());
             }
-            _ => {}
-        }
+        };
 
-        let prefix_rule = prefix_rule.unwrap();
         let mut left_expr: Ast = prefix_rule(self);
 
         loop {
@@ -384,10 +1358,6 @@ impl Parser {
         left_expr
     }
 
-    pub fn offset(&self) -> i32 {
-        self.scope.offset
-    }
-
     fn debug_tokens(&self, prefix: &str) {
         println!(
             "{prefix} curr token: {:#?}, peek token: {:#?}",
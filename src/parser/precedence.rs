@@ -5,35 +5,92 @@ use crate::tokenizer::tokentype::TokenType;
 pub enum Precedence {
     PrecedenceNone = 1,
 
-    PrecedenceAssignment = 2, /* = */
+    PrecedenceComma = 2, /* , */
 
-    PrecedenceEquality = 5, /* ==, != */
+    PrecedenceAssignment = 3, /* =, +=, -=, ... */
 
-    PrecedenceComparison = 6, /* <, >, <=, >= */
+    PrecedenceTernary = 4, /* ?: */
 
-    PrecedenceTerm = 7, /* +, - */
+    PrecedenceLogicalOr = 5, /* || */
 
-    PrecedenceFactor = 8, /* *, / */
+    PrecedenceLogicalAnd = 6, /* && */
 
-    PrecedenceUnary = 9, /* !, - */
+    PrecedenceBitOr = 7, /* | */
 
-    PrecedenceCall = 10, /* ., () */
+    PrecedenceBitXor = 8, /* ^ */
+
+    PrecedenceBitAnd = 9, /* & */
+
+    PrecedenceEquality = 10, /* ==, != */
+
+    PrecedenceComparison = 11, /* <, >, <=, >= */
+
+    PrecedenceShift = 12, /* <<, >> */
+
+    PrecedenceTerm = 13, /* +, - */
+
+    PrecedenceFactor = 14, /* *, /, % */
+
+    PrecedenceUnary = 15, /* !, ~, - */
+
+    PrecedenceCall = 16, /* ., ->, (), [], postfix ++, -- */
+}
+
+impl Precedence {
+    /// The level binding one step tighter. The right operand of a left
+    /// associative operator is parsed at it, so that `a - b + c` is
+    /// `(a - b) + c`.
+    pub fn next(&self) -> Precedence {
+        match self {
+            Precedence::PrecedenceNone => Precedence::PrecedenceComma,
+            Precedence::PrecedenceComma => Precedence::PrecedenceAssignment,
+            Precedence::PrecedenceAssignment => Precedence::PrecedenceTernary,
+            Precedence::PrecedenceTernary => Precedence::PrecedenceLogicalOr,
+            Precedence::PrecedenceLogicalOr => Precedence::PrecedenceLogicalAnd,
+            Precedence::PrecedenceLogicalAnd => Precedence::PrecedenceBitOr,
+            Precedence::PrecedenceBitOr => Precedence::PrecedenceBitXor,
+            Precedence::PrecedenceBitXor => Precedence::PrecedenceBitAnd,
+            Precedence::PrecedenceBitAnd => Precedence::PrecedenceEquality,
+            Precedence::PrecedenceEquality => Precedence::PrecedenceComparison,
+            Precedence::PrecedenceComparison => Precedence::PrecedenceShift,
+            Precedence::PrecedenceShift => Precedence::PrecedenceTerm,
+            Precedence::PrecedenceTerm => Precedence::PrecedenceFactor,
+            Precedence::PrecedenceFactor => Precedence::PrecedenceUnary,
+            Precedence::PrecedenceUnary | Precedence::PrecedenceCall => Precedence::PrecedenceCall,
+        }
+    }
 }
 
 pub fn get_precedence(tok: &TokenType) -> Precedence {
     match tok {
+        TokenType::TokenComma => Precedence::PrecedenceComma,
+        TokenType::TokenQuestion => Precedence::PrecedenceTernary,
+        TokenType::TokenPipePipe => Precedence::PrecedenceLogicalOr,
+        TokenType::TokenAndAnd => Precedence::PrecedenceLogicalAnd,
+        TokenType::TokenPipe => Precedence::PrecedenceBitOr,
+        TokenType::TokenCaret => Precedence::PrecedenceBitXor,
+        TokenType::TokenAddr => Precedence::PrecedenceBitAnd,
         TokenType::TokenEqualEqual => Precedence::PrecedenceEquality,
         TokenType::TokenBangEqual => Precedence::PrecedenceEquality,
         TokenType::TokenEqual => Precedence::PrecedenceAssignment,
+        _ if tok.compound_op().is_some() => Precedence::PrecedenceAssignment,
         TokenType::TokenLess => Precedence::PrecedenceComparison,
         TokenType::TokenLessEqual => Precedence::PrecedenceComparison,
         TokenType::TokenGreater => Precedence::PrecedenceComparison,
         TokenType::TokenGreaterEqual => Precedence::PrecedenceComparison,
+        TokenType::TokenLessLess => Precedence::PrecedenceShift,
+        TokenType::TokenGreaterGreater => Precedence::PrecedenceShift,
         TokenType::TokenMinus => Precedence::PrecedenceTerm,
         TokenType::TokenPlus => Precedence::PrecedenceTerm,
         TokenType::TokenSlash => Precedence::PrecedenceFactor,
         TokenType::TokenStar => Precedence::PrecedenceFactor,
+        TokenType::TokenPercent => Precedence::PrecedenceFactor,
         TokenType::TokenLeftParen => Precedence::PrecedenceCall,
+        TokenType::TokenLeftBracket => Precedence::PrecedenceCall,
+        TokenType::TokenDot => Precedence::PrecedenceCall,
+        TokenType::TokenArrow => Precedence::PrecedenceCall,
+        TokenType::TokenPlusPlus => Precedence::PrecedenceCall,
+        TokenType::TokenMinusMinus => Precedence::PrecedenceCall,
         _ => Precedence::PrecedenceNone,
     }
 }
@@ -0,0 +1,112 @@
+//! Predefined and builtin macros. They live with the preprocessor rather
+//! than the tokenizer because they are macros: `#ifdef`, `#undef`, `-U` and
+//! `-dM` must see them in the macro table, and `__LINE__` and `__FILE__`
+//! expand to where the macro is used, which only the expansion knows.
+
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::preprocessor::Preprocessor;
+use crate::tokenizer::token::Token;
+use crate::tokenizer::tokentype::TokenType;
+
+/// Macros whose value depends on where they are used.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BuiltinMacro {
+    File,
+    Line,
+    Counter,
+}
+
+/// The macros of the target and the language every source sees.
+static PREDEFINED: &str = "\
+#define __STDC__ 1
+#define __STDC_VERSION__ 201112L
+#define __STDC_HOSTED__ 1
+#define __x86_64__ 1
+#define __x86_64 1
+#define __amd64__ 1
+#define __amd64 1
+#define __LP64__ 1
+#define _LP64 1
+#define __linux__ 1
+#define __linux 1
+#define __unix__ 1
+#define __unix 1
+#define __ELF__ 1
+#define __CHAR_BIT__ 8
+#define __SIZEOF_SHORT__ 2
+#define __SIZEOF_INT__ 4
+#define __SIZEOF_LONG__ 8
+#define __SIZEOF_POINTER__ 8
+";
+
+static MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// The predefined macros as `#define` lines, `__DATE__` and `__TIME__`
+/// being the UTC time `epoch`, in seconds since 1970, or the current time.
+pub fn predefined(epoch: Option<u64>) -> String {
+    let epoch = epoch.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+    });
+
+    let (year, month, day) = civil_from_days((epoch / 86400) as i64);
+    let secs = epoch % 86400;
+
+    format!(
+        "{}#define __DATE__ \"{} {:2} {}\"\n#define __TIME__ \"{:02}:{:02}:{:02}\"\n",
+        PREDEFINED,
+        MONTHS[month - 1],
+        day,
+        year,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// The year, month and day of the days since 1970-01-01 in the proleptic
+/// Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, usize, i64) {
+    // shifted so that a 400 year era starts on March 1st
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    (year, month as usize, day)
+}
+
+impl Preprocessor {
+    /// The token `tok` naming a builtin macro stands for. The position is
+    /// that of the invocation in the source, outside of any macro.
+    pub(super) fn expand_builtin(&mut self, builtin: BuiltinMacro, tok: &Token) -> Token {
+        let at = tok.source_token();
+
+        let (kind, val) = match builtin {
+            BuiltinMacro::File => {
+                let name = at.file.name.replace('\\', "\\\\").replace('"', "\\\"");
+                (TokenType::TokenString, format!("\"{}\"", name))
+            }
+            BuiltinMacro::Line => (TokenType::TokenNumber, at.line.to_string()),
+            BuiltinMacro::Counter => {
+                self.counter += 1;
+                (TokenType::TokenNumber, (self.counter - 1).to_string())
+            }
+        };
+
+        let mut expanded = Token::new(kind, &val, &tok.file, tok.line, tok.col);
+        expanded.has_space = tok.has_space;
+        expanded.origin = Some(Rc::new(tok.clone()));
+        expanded
+    }
+}
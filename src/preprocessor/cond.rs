@@ -0,0 +1,226 @@
+use crate::preprocessor::expr::ExprEval;
+use crate::preprocessor::Preprocessor;
+use crate::tokenizer::token::Token;
+use crate::tokenizer::tokentype::TokenType;
+
+#[derive(Clone, Copy, PartialEq)]
+enum CondCtx {
+    If,
+    Elif,
+    Else,
+}
+
+/// An `#if`, `#ifdef` or `#ifndef` whose `#endif` has not been read yet.
+pub(super) struct Cond {
+    ctx: CondCtx,
+    // the directive name, where an unterminated group is reported
+    token: Token,
+    // one of the groups so far was read, the others are skipped
+    included: bool,
+    // the include depth of the file the directive is in
+    depth: usize,
+}
+
+impl Preprocessor {
+    pub(super) fn if_directive(&mut self, directive: &Token, line: Vec<Token>) {
+        let included = self.eval_condition(directive, line);
+        self.push_cond(directive, included);
+    }
+
+    /// `#ifdef` when `expected` is true, `#ifndef` otherwise.
+    pub(super) fn ifdef(&mut self, directive: &Token, line: Vec<Token>, expected: bool) {
+        let defined = match line.first() {
+            Some(name) if Self::is_identifier(name) => self.macros.contains_key(&name.val),
+            Some(tok) => {
+                self.error(tok, "macro name must be an identifier".to_string());
+                false
+            }
+            None => {
+                self.error(directive, "macro name missing".to_string());
+                false
+            }
+        };
+        self.push_cond(directive, defined == expected);
+    }
+
+    pub(super) fn elif(&mut self, directive: &Token, line: Vec<Token>) {
+        let included = match self.continue_cond(directive, CondCtx::Elif) {
+            Some(included) => included,
+            None => return,
+        };
+
+        // a group was read already, the condition is not even evaluated
+        if included {
+            return self.skip_cond_group();
+        }
+
+        let included = self.eval_condition(directive, line);
+        if let Some(cond) = self.conds.last_mut() {
+            cond.included = included;
+        }
+        if !included {
+            self.skip_cond_group();
+        }
+    }
+
+    pub(super) fn else_directive(&mut self, directive: &Token) {
+        let included = match self.continue_cond(directive, CondCtx::Else) {
+            Some(included) => included,
+            None => return,
+        };
+
+        if let Some(cond) = self.conds.last_mut() {
+            cond.included = true;
+        }
+        if included {
+            self.skip_cond_group();
+        }
+    }
+
+    pub(super) fn endif(&mut self, directive: &Token) {
+        if self.current_cond(directive, "#endif") {
+            self.conds.pop();
+        }
+    }
+
+    /// Reports the conditionals of the file that just ended as unterminated.
+    pub(super) fn close_conds(&mut self) {
+        while let Some(cond) = self.conds.last() {
+            if cond.depth < self.include_depth {
+                break;
+            }
+            let tok = cond.token.clone();
+            self.conds.pop();
+            self.error(&tok, "unterminated conditional directive".to_string());
+        }
+    }
+
+    /// Moves on to the `#elif` or `#else` group of the innermost
+    /// conditional, whether one of its groups was read already.
+    fn continue_cond(&mut self, directive: &Token, ctx: CondCtx) -> Option<bool> {
+        let name = match ctx {
+            CondCtx::Elif => "#elif",
+            _ => "#else",
+        };
+        if !self.current_cond(directive, name) {
+            return None;
+        }
+
+        let cond = self.conds.last_mut()?;
+        if cond.ctx == CondCtx::Else {
+            let msg = format!("{} after #else", name);
+            self.error(directive, msg);
+            return None;
+        }
+        cond.ctx = ctx;
+        Some(cond.included)
+    }
+
+    /// Whether there is a conditional for `directive` to continue, which
+    /// must be in the same file.
+    fn current_cond(&mut self, directive: &Token, name: &str) -> bool {
+        let depth = self.include_depth;
+        if self.conds.last().is_none_or(|cond| cond.depth != depth) {
+            self.error(directive, format!("{} without #if", name));
+            return false;
+        }
+        true
+    }
+
+    fn push_cond(&mut self, directive: &Token, included: bool) {
+        self.conds.push(Cond {
+            ctx: CondCtx::If,
+            token: directive.clone(),
+            included,
+            depth: self.include_depth,
+        });
+
+        if !included {
+            self.skip_cond_group();
+        }
+    }
+
+    /// Skips the lines of a group that is not read, up to the `#elif`,
+    /// `#else` or `#endif` ending it. Nested conditionals are skipped as a
+    /// whole.
+    fn skip_cond_group(&mut self) {
+        let mut nesting = 0;
+
+        while let Some(tok) = self.pending.last() {
+            if tok.is(TokenType::TokenEof) {
+                return;
+            }
+
+            if tok.is(TokenType::TokenHash) && tok.at_bol {
+                let name = self.pending.iter().rev().nth(1).filter(|t| !t.at_bol);
+                match name.map(|t| t.val.as_str()) {
+                    Some("if" | "ifdef" | "ifndef") => nesting += 1,
+                    Some("elif" | "else") if nesting == 0 => return,
+                    Some("endif") if nesting == 0 => return,
+                    Some("endif") => nesting -= 1,
+                    _ => {}
+                }
+            }
+
+            self.next_token();
+        }
+    }
+
+    /// The value of the expression of `#if` or `#elif`, `defined NAME` and
+    /// `defined(NAME)` being replaced before the macros are expanded.
+    fn eval_condition(&mut self, directive: &Token, line: Vec<Token>) -> bool {
+        let mut tokens = Vec::new();
+        let mut line = line.into_iter().peekable();
+
+        while let Some(tok) = line.next() {
+            if tok.val != "defined" || tok.is(TokenType::TokenError) {
+                tokens.push(tok);
+                continue;
+            }
+
+            let paren = line.next_if(|t| t.is(TokenType::TokenLeftParen));
+            let name = match line.next() {
+                Some(name) if Self::is_identifier(&name) => name,
+                Some(t) => {
+                    self.error(&t, "macro name must be an identifier".to_string());
+                    return false;
+                }
+                None => {
+                    self.error(&tok, "macro name missing".to_string());
+                    return false;
+                }
+            };
+            if paren.is_some() && line.next_if(|t| t.is(TokenType::TokenRightParen)).is_none() {
+                self.error(&name, "missing ')' after 'defined'".to_string());
+                return false;
+            }
+
+            let val = match self.macros.contains_key(&name.val) {
+                true => "1",
+                false => "0",
+            };
+            tokens.push(Token::new(
+                TokenType::TokenNumber,
+                val,
+                &tok.file,
+                tok.line,
+                tok.col,
+            ));
+        }
+
+        let tokens = self.expand_all(&tokens);
+        if tokens.is_empty() {
+            self.error(directive, format!("#{} with no expression", directive.val));
+            return false;
+        }
+
+        let end = tokens.last().unwrap_or(directive);
+        match ExprEval::new(&tokens, end).eval() {
+            Ok(val) => val != 0,
+            Err((tok, msg)) => {
+                self.error(&tok, msg);
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use crate::preprocessor::Preprocessor;
+use crate::tokenizer::literal::int_value;
+use crate::tokenizer::token::Token;
+use crate::tokenizer::tokentype::TokenType;
+
+type EvalResult = Result<i64, (Token, String)>;
+
+/// Evaluates the constant expression of `#if` and `#elif`, after `defined`
+/// and the macros have been replaced. Identifiers left are 0.
+///
+/// Operands that are never evaluated, like the right of `0 && x`, are still
+/// parsed but cannot fail to divide.
+pub(super) struct ExprEval<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    // where a missing operand is reported
+    end: &'a Token,
+}
+
+impl<'a> ExprEval<'a> {
+    pub fn new(tokens: &'a [Token], end: &'a Token) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            end,
+        }
+    }
+
+    /// The value of the whole expression, which must not be followed by
+    /// anything else.
+    pub fn eval(&mut self) -> EvalResult {
+        let val = self.conditional(true)?;
+        match self.peek() {
+            Some(tok) => Err((
+                tok.clone(),
+                format!("missing binary operator before '{}'", tok.val),
+            )),
+            None => Ok(val),
+        }
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Consumes the next token when it is one of `kinds`.
+    fn next_if(&mut self, kinds: &[TokenType]) -> Option<&'a Token> {
+        let tok = self.peek().filter(|tok| kinds.contains(&tok.kind))?;
+        self.pos += 1;
+        Some(tok)
+    }
+
+    fn conditional(&mut self, live: bool) -> EvalResult {
+        let cond = self.logical_or(live)?;
+        if self.next_if(&[TokenType::TokenQuestion]).is_none() {
+            return Ok(cond);
+        }
+
+        let then = self.conditional(live && cond != 0)?;
+        if self.next_if(&[TokenType::TokenColon]).is_none() {
+            let at = self.peek().unwrap_or(self.end).clone();
+            return Err((at, "expected ':' in preprocessor expression".to_string()));
+        }
+        let alt = self.conditional(live && cond == 0)?;
+
+        Ok(if cond != 0 { then } else { alt })
+    }
+
+    fn logical_or(&mut self, live: bool) -> EvalResult {
+        let mut val = self.logical_and(live)?;
+        while self.next_if(&[TokenType::TokenPipePipe]).is_some() {
+            let right = self.logical_and(live && val == 0)?;
+            val = (val != 0 || right != 0) as i64;
+        }
+        Ok(val)
+    }
+
+    fn logical_and(&mut self, live: bool) -> EvalResult {
+        let mut val = self.bit_or(live)?;
+        while self.next_if(&[TokenType::TokenAndAnd]).is_some() {
+            let right = self.bit_or(live && val != 0)?;
+            val = (val != 0 && right != 0) as i64;
+        }
+        Ok(val)
+    }
+
+    fn bit_or(&mut self, live: bool) -> EvalResult {
+        let mut val = self.bit_xor(live)?;
+        while self.next_if(&[TokenType::TokenPipe]).is_some() {
+            val |= self.bit_xor(live)?;
+        }
+        Ok(val)
+    }
+
+    fn bit_xor(&mut self, live: bool) -> EvalResult {
+        let mut val = self.bit_and(live)?;
+        while self.next_if(&[TokenType::TokenCaret]).is_some() {
+            val ^= self.bit_and(live)?;
+        }
+        Ok(val)
+    }
+
+    fn bit_and(&mut self, live: bool) -> EvalResult {
+        let mut val = self.equality(live)?;
+        while self.next_if(&[TokenType::TokenAddr]).is_some() {
+            val &= self.equality(live)?;
+        }
+        Ok(val)
+    }
+
+    fn equality(&mut self, live: bool) -> EvalResult {
+        let mut val = self.relational(live)?;
+        let ops = [TokenType::TokenEqualEqual, TokenType::TokenBangEqual];
+        while let Some(op) = self.next_if(&ops) {
+            let right = self.relational(live)?;
+            val = match op.kind {
+                TokenType::TokenEqualEqual => (val == right) as i64,
+                _ => (val != right) as i64,
+            };
+        }
+        Ok(val)
+    }
+
+    fn relational(&mut self, live: bool) -> EvalResult {
+        let mut val = self.shift(live)?;
+        let ops = [
+            TokenType::TokenLess,
+            TokenType::TokenLessEqual,
+            TokenType::TokenGreater,
+            TokenType::TokenGreaterEqual,
+        ];
+        while let Some(op) = self.next_if(&ops) {
+            let right = self.shift(live)?;
+            val = match op.kind {
+                TokenType::TokenLess => (val < right) as i64,
+                TokenType::TokenLessEqual => (val <= right) as i64,
+                TokenType::TokenGreater => (val > right) as i64,
+                _ => (val >= right) as i64,
+            };
+        }
+        Ok(val)
+    }
+
+    fn shift(&mut self, live: bool) -> EvalResult {
+        let mut val = self.additive(live)?;
+        let ops = [TokenType::TokenLessLess, TokenType::TokenGreaterGreater];
+        while let Some(op) = self.next_if(&ops) {
+            let right = self.additive(live)?;
+            val = match op.kind {
+                TokenType::TokenLessLess => val.wrapping_shl(right as u32),
+                _ => val.wrapping_shr(right as u32),
+            };
+        }
+        Ok(val)
+    }
+
+    fn additive(&mut self, live: bool) -> EvalResult {
+        let mut val = self.multiplicative(live)?;
+        while let Some(op) = self.next_if(&[TokenType::TokenPlus, TokenType::TokenMinus]) {
+            let right = self.multiplicative(live)?;
+            val = match op.kind {
+                TokenType::TokenPlus => val.wrapping_add(right),
+                _ => val.wrapping_sub(right),
+            };
+        }
+        Ok(val)
+    }
+
+    fn multiplicative(&mut self, live: bool) -> EvalResult {
+        let mut val = self.unary(live)?;
+        let ops = [
+            TokenType::TokenStar,
+            TokenType::TokenSlash,
+            TokenType::TokenPercent,
+        ];
+        while let Some(op) = self.next_if(&ops) {
+            let right = self.unary(live)?;
+            if op.is(TokenType::TokenStar) {
+                val = val.wrapping_mul(right);
+                continue;
+            }
+
+            if right == 0 {
+                if live {
+                    return Err((
+                        op.clone(),
+                        "division by zero in preprocessor expression".to_string(),
+                    ));
+                }
+                continue;
+            }
+            val = match op.kind {
+                TokenType::TokenSlash => val.wrapping_div(right),
+                _ => val.wrapping_rem(right),
+            };
+        }
+        Ok(val)
+    }
+
+    fn unary(&mut self, live: bool) -> EvalResult {
+        let ops = [
+            TokenType::TokenPlus,
+            TokenType::TokenMinus,
+            TokenType::TokenBang,
+            TokenType::TokenTilde,
+        ];
+        match self.next_if(&ops) {
+            Some(op) => {
+                let val = self.unary(live)?;
+                Ok(match op.kind {
+                    TokenType::TokenPlus => val,
+                    TokenType::TokenMinus => val.wrapping_neg(),
+                    TokenType::TokenTilde => !val,
+                    _ => (val == 0) as i64,
+                })
+            }
+            None => self.primary(live),
+        }
+    }
+
+    fn primary(&mut self, live: bool) -> EvalResult {
+        let tok = match self.peek() {
+            Some(tok) => tok,
+            None => {
+                let msg = "expected a value in preprocessor expression".to_string();
+                return Err((self.end.clone(), msg));
+            }
+        };
+        self.pos += 1;
+
+        match tok.kind {
+            TokenType::TokenLeftParen => {
+                let val = self.conditional(live)?;
+                match self.next_if(&[TokenType::TokenRightParen]) {
+                    Some(_) => Ok(val),
+                    None => {
+                        let at = self.peek().unwrap_or(self.end).clone();
+                        Err((at, "missing ')' in preprocessor expression".to_string()))
+                    }
+                }
+            }
+            TokenType::TokenNumber => int_value(tok).map(|lit| lit.value as i64).map_err(|_| {
+                let msg = format!(
+                    "invalid integer constant '{}' in preprocessor expression",
+                    tok.val
+                );
+                (tok.clone(), msg)
+            }),
+            // what is left of identifiers after macro expansion
+            _ if Preprocessor::is_identifier(tok) => Ok(0),
+            _ => {
+                let msg = format!(
+                    "expected a value in preprocessor expression but got '{}'",
+                    tok.val
+                );
+                Err((tok.clone(), msg))
+            }
+        }
+    }
+}
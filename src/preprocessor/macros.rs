@@ -0,0 +1,523 @@
+use std::rc::Rc;
+
+use crate::preprocessor::builtin::BuiltinMacro;
+use crate::preprocessor::Preprocessor;
+use crate::tokenizer::source::SourceFile;
+use crate::tokenizer::token::Token;
+use crate::tokenizer::tokentype::TokenType;
+use crate::tokenizer::Tokenizer;
+
+/// The name the variable arguments of a `...` macro are referred to by.
+static VA_ARGS: &str = "__VA_ARGS__";
+
+#[derive(Debug, Clone)]
+pub struct Macro {
+    // the parameter names of a function-like macro, `None` for an
+    // object-like one
+    pub params: Option<Vec<String>>,
+    // takes extra arguments as `__VA_ARGS__` after the named ones
+    pub variadic: bool,
+    pub body: Vec<Token>,
+    // computes the expansion instead of the body
+    pub builtin: Option<BuiltinMacro>,
+}
+
+impl Macro {
+    /// A macro expanded by the preprocessor itself, like `__LINE__`.
+    pub fn builtin(builtin: BuiltinMacro) -> Self {
+        Self {
+            params: None,
+            variadic: false,
+            body: Vec::new(),
+            builtin: Some(builtin),
+        }
+    }
+
+    /// The `#define` line defining the macro `name` as it is.
+    pub fn definition(&self, name: &str) -> String {
+        let mut def = format!("#define {}", name);
+
+        if let Some(params) = &self.params {
+            let mut params = params.clone();
+            if self.variadic {
+                params.push("...".to_string());
+            }
+            def.push_str(&format!("({})", params.join(",")));
+        }
+
+        if !self.body.is_empty() {
+            def.push(' ');
+            def.push_str(&spell(&self.body));
+        }
+        def
+    }
+}
+
+/// The text of `tokens`, with a space wherever there was whitespace
+/// between them.
+pub fn spell(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    for (i, tok) in tokens.iter().enumerate() {
+        if i > 0 && tok.has_space {
+            text.push(' ');
+        }
+        text.push_str(&tok.val);
+    }
+    text
+}
+
+/// The arguments of a macro invocation, as written.
+struct MacroArgs {
+    named: Vec<(String, Vec<Token>)>,
+    variadic: Option<Vec<Token>>,
+}
+
+impl MacroArgs {
+    fn get(&self, tok: &Token) -> Option<&Vec<Token>> {
+        if tok.is(TokenType::TokenError) {
+            return None;
+        }
+        if tok.val == VA_ARGS {
+            return self.variadic.as_ref();
+        }
+        self.named
+            .iter()
+            .find(|(name, _)| *name == tok.val)
+            .map(|(_, arg)| arg)
+    }
+}
+
+impl Preprocessor {
+    pub(super) fn define(&mut self, directive: &Token, line: Vec<Token>) {
+        let mut line = line.into_iter().peekable();
+        let name = match line.next() {
+            Some(name) if Self::is_identifier(&name) => name,
+            Some(tok) => return self.error(&tok, "macro name must be an identifier".to_string()),
+            None => return self.error(directive, "macro name missing".to_string()),
+        };
+
+        // only a parenthesis right after the name starts a parameter list
+        let mut params = None;
+        let mut variadic = false;
+        if let Some(paren) = line.next_if(|t| t.is(TokenType::TokenLeftParen) && !t.has_space) {
+            match Self::macro_params(&paren, &mut line) {
+                Ok((names, dots)) => {
+                    params = Some(names);
+                    variadic = dots;
+                }
+                Err((tok, msg)) => return self.error(&tok, msg),
+            }
+        }
+
+        let body: Vec<Token> = line.collect();
+        if let Err((tok, msg)) = Self::check_body(&body, params.as_deref(), variadic) {
+            return self.error(tok, msg);
+        }
+
+        self.macros.insert(
+            name.val,
+            Macro {
+                params,
+                variadic,
+                body,
+                builtin: None,
+            },
+        );
+    }
+
+    /// The parameter names after the `(` of a function-like macro, and
+    /// whether they end with `...`.
+    fn macro_params(
+        paren: &Token,
+        line: &mut impl Iterator<Item = Token>,
+    ) -> Result<(Vec<String>, bool), (Token, String)> {
+        let mut names: Vec<String> = Vec::new();
+        let mut last = paren.clone();
+
+        loop {
+            let tok = match line.next() {
+                Some(tok) => tok,
+                None => return Err((last, "missing ')' in macro parameter list".to_string())),
+            };
+
+            if names.is_empty() && tok.is(TokenType::TokenRightParen) {
+                return Ok((names, false));
+            }
+
+            if tok.is(TokenType::TokenEllipsis) {
+                return match line.next() {
+                    Some(t) if t.is(TokenType::TokenRightParen) => Ok((names, true)),
+                    Some(t) => Err((t, "missing ')' in macro parameter list".to_string())),
+                    None => Err((tok, "missing ')' in macro parameter list".to_string())),
+                };
+            }
+
+            if !Self::is_identifier(&tok) {
+                let msg = format!("expected a macro parameter name but got '{}'", tok.val);
+                return Err((tok, msg));
+            }
+            if names.contains(&tok.val) {
+                let msg = format!("duplicate macro parameter '{}'", tok.val);
+                return Err((tok, msg));
+            }
+            names.push(tok.val.clone());
+
+            match line.next() {
+                Some(t) if t.is(TokenType::TokenRightParen) => return Ok((names, false)),
+                Some(t) if t.is(TokenType::TokenComma) => last = t,
+                Some(t) => {
+                    let msg = format!(
+                        "expected ',' or ')' in macro parameter list but got '{}'",
+                        t.val
+                    );
+                    return Err((t, msg));
+                }
+                None => return Err((tok, "missing ')' in macro parameter list".to_string())),
+            }
+        }
+    }
+
+    /// `##` needs an operand on both sides and the `#` of a function-like
+    /// macro a parameter after it.
+    fn check_body<'a>(
+        body: &'a [Token],
+        params: Option<&[String]>,
+        variadic: bool,
+    ) -> Result<(), (&'a Token, String)> {
+        for end in [body.first(), body.last()].into_iter().flatten() {
+            if end.is(TokenType::TokenHashHash) {
+                let msg = "'##' cannot appear at either end of macro expansion".to_string();
+                return Err((end, msg));
+            }
+        }
+
+        let params = match params {
+            Some(params) => params,
+            None => return Ok(()),
+        };
+        let is_param = |tok: &Token| params.contains(&tok.val) || (variadic && tok.val == VA_ARGS);
+
+        for (i, tok) in body.iter().enumerate() {
+            if tok.is(TokenType::TokenHash) && !body.get(i + 1).is_some_and(is_param) {
+                let msg = "'#' is not followed by a macro parameter".to_string();
+                return Err((tok, msg));
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn undef(&mut self, directive: &Token, line: Vec<Token>) {
+        match line.first() {
+            Some(name) if Self::is_identifier(name) => {
+                self.macros.remove(&name.val);
+            }
+            Some(tok) => self.error(tok, "macro name must be an identifier".to_string()),
+            None => self.error(directive, "macro name missing".to_string()),
+        }
+    }
+
+    /// Replaces a macro name, and the arguments of a function-like macro,
+    /// by the body of the macro, which is then scanned for more macros.
+    ///
+    /// The expanded tokens remember the macro in their hideset so that a
+    /// macro referring to itself expands only once. For a function-like
+    /// macro that is the macros hiding both its name and the closing
+    /// parenthesis, so that a macro whose arguments come from its own body
+    /// still expands again.
+    pub(super) fn expand_macro(&mut self, tok: &Token) -> bool {
+        if !Self::is_identifier(tok) || tok.hideset.contains(&tok.val) {
+            return false;
+        }
+
+        let m = match self.macros.get(&tok.val) {
+            Some(m) => m.clone(),
+            None => return false,
+        };
+
+        if let Some(builtin) = m.builtin {
+            let expanded = self.expand_builtin(builtin, tok);
+            self.push_tokens(vec![expanded]);
+            return true;
+        }
+
+        let (args, mut hideset) = match &m.params {
+            None => (None, tok.hideset.clone()),
+            Some(params) => {
+                // the name alone does not invoke a function-like macro
+                match self.pending.last() {
+                    Some(next) if next.is(TokenType::TokenLeftParen) => {}
+                    _ => return false,
+                }
+
+                let (args, rparen) = match self.macro_args(tok, params, m.variadic) {
+                    Some(args) => args,
+                    None => return true,
+                };
+
+                let hideset = tok
+                    .hideset
+                    .iter()
+                    .filter(|name| rparen.hideset.contains(name))
+                    .cloned()
+                    .collect();
+                (Some(args), hideset)
+            }
+        };
+        hideset.push(tok.val.clone());
+
+        let mut body = self.substitute(&m, args.as_ref());
+        let origin = Rc::new(tok.clone());
+        for t in body.iter_mut() {
+            t.hideset.extend(hideset.iter().cloned());
+            if t.origin.is_none() {
+                t.origin = Some(origin.clone());
+            }
+        }
+        if let Some(first) = body.first_mut() {
+            first.has_space = tok.has_space;
+        }
+
+        self.push_tokens(body);
+        true
+    }
+
+    /// Reads the arguments of a function-like macro from its `(` to the
+    /// matching `)`, which is returned as well. Commas only separate
+    /// arguments outside nested parentheses.
+    fn macro_args(
+        &mut self,
+        name: &Token,
+        params: &[String],
+        variadic: bool,
+    ) -> Option<(MacroArgs, Token)> {
+        self.next_token();
+
+        let mut args: Vec<Vec<Token>> = Vec::new();
+        let mut arg = Vec::new();
+        let mut depth = 0;
+
+        let rparen = loop {
+            let tok = self.next_token();
+            match tok.kind {
+                TokenType::TokenEof => {
+                    let msg = format!("unterminated argument list invoking macro '{}'", name.val);
+                    self.error(name, msg);
+                    self.pending.push(tok);
+                    return None;
+                }
+                TokenType::TokenLeftParen => depth += 1,
+                TokenType::TokenRightParen if depth == 0 => {
+                    args.push(arg);
+                    break tok;
+                }
+                TokenType::TokenRightParen => depth -= 1,
+                // the variable arguments keep their commas
+                TokenType::TokenComma
+                    if depth == 0 && !(variadic && args.len() == params.len()) =>
+                {
+                    args.push(std::mem::take(&mut arg));
+                    continue;
+                }
+                _ => {}
+            }
+            arg.push(tok);
+        };
+
+        // `f()` passes no argument rather than an empty one
+        if params.is_empty() && args.len() == 1 && args[0].is_empty() {
+            args.clear();
+        }
+
+        let variadic_args = match variadic && args.len() > params.len() {
+            true => args.pop(),
+            false => None,
+        };
+
+        if args.len() != params.len() {
+            let msg = match args.len() < params.len() {
+                true => format!(
+                    "macro '{}' requires {} arguments, but only {} given",
+                    name.val,
+                    params.len(),
+                    args.len()
+                ),
+                false => format!(
+                    "macro '{}' passed {} arguments, but takes just {}",
+                    name.val,
+                    args.len(),
+                    params.len()
+                ),
+            };
+            self.error(&rparen, msg);
+            return None;
+        }
+
+        // the variable arguments may be left out altogether
+        let variadic_args = match variadic {
+            true => Some(variadic_args.unwrap_or_default()),
+            false => None,
+        };
+
+        let args = MacroArgs {
+            named: params.iter().cloned().zip(args).collect(),
+            variadic: variadic_args,
+        };
+        Some((args, rparen))
+    }
+
+    /// The body of `m` with its parameters replaced by the arguments. An
+    /// argument is fully macro expanded first unless it is the operand of
+    /// `#` or `##`.
+    fn substitute(&mut self, m: &Macro, args: Option<&MacroArgs>) -> Vec<Token> {
+        let param = |tok: &Token| args.and_then(|args| args.get(tok));
+        let body = &m.body;
+        let mut out: Vec<Token> = Vec::new();
+
+        let mut i = 0;
+        while i < body.len() {
+            let tok = &body[i];
+            let next = body.get(i + 1);
+
+            // `#param` is the spelling of the argument as a string literal
+            if tok.is(TokenType::TokenHash) {
+                if let Some(arg) = next.and_then(param) {
+                    out.push(Self::stringize(tok, arg));
+                    i += 2;
+                    continue;
+                }
+            }
+
+            // `, ## __VA_ARGS__` drops the comma when there are no
+            // variable arguments
+            if tok.is(TokenType::TokenComma)
+                && next.is_some_and(|t| t.is(TokenType::TokenHashHash))
+                && body.get(i + 2).is_some_and(|t| t.val == VA_ARGS)
+            {
+                if let Some(va_args) = body.get(i + 2).and_then(param) {
+                    if va_args.is_empty() {
+                        i += 3;
+                    } else {
+                        out.push(tok.clone());
+                        i += 2;
+                    }
+                    continue;
+                }
+            }
+
+            // `define` makes sure that `##` has operands
+            if tok.is(TokenType::TokenHashHash) {
+                let (lhs, rhs) = match (out.pop(), next) {
+                    (Some(lhs), Some(rhs)) => (lhs, rhs),
+                    _ => unreachable!("'##' at the end of a macro body"),
+                };
+
+                match param(rhs) {
+                    Some(arg) if arg.is_empty() => out.push(lhs),
+                    Some(arg) => {
+                        out.push(self.paste(&lhs, &arg[0]));
+                        out.extend(arg[1..].iter().cloned());
+                    }
+                    None => out.push(self.paste(&lhs, rhs)),
+                }
+                i += 2;
+                continue;
+            }
+
+            if let Some(arg) = param(tok) {
+                let pasted = next.is_some_and(|t| t.is(TokenType::TokenHashHash));
+
+                // an empty left operand leaves the right one as it is
+                if pasted && arg.is_empty() {
+                    let rhs = &body[i + 2];
+                    match param(rhs) {
+                        Some(arg) => out.extend(arg.iter().cloned()),
+                        None => out.push(rhs.clone()),
+                    }
+                    i += 3;
+                    continue;
+                }
+
+                let mut arg = match pasted {
+                    true => arg.clone(),
+                    false => self.expand_all(arg),
+                };
+                if let Some(first) = arg.first_mut() {
+                    first.has_space = tok.has_space;
+                }
+                out.extend(arg);
+                i += 1;
+                continue;
+            }
+
+            out.push(tok.clone());
+            i += 1;
+        }
+
+        out
+    }
+
+    /// Macro expands `tokens` on their own, as if they were the rest of the
+    /// file.
+    pub(super) fn expand_all(&mut self, tokens: &[Token]) -> Vec<Token> {
+        let eof = match tokens.last() {
+            Some(tok) => Token::new(TokenType::TokenEof, "", &tok.file, tok.line, tok.col),
+            None => return Vec::new(),
+        };
+
+        let pending = std::mem::replace(&mut self.pending, vec![eof]);
+        self.push_tokens(tokens.to_vec());
+
+        let mut out = Vec::new();
+        loop {
+            let tok = self.next_token();
+            if tok.is(TokenType::TokenEof) {
+                break;
+            }
+            if self.expand_macro(&tok) {
+                continue;
+            }
+            out.push(tok);
+        }
+
+        self.pending = pending;
+        out
+    }
+
+    /// The string literal spelling `arg`.
+    fn stringize(hash: &Token, arg: &[Token]) -> Token {
+        let escaped = spell(arg).replace('\\', "\\\\").replace('"', "\\\"");
+        let val = format!("\"{}\"", escaped);
+        let mut tok = Token::new(
+            TokenType::TokenString,
+            &val,
+            &hash.file,
+            hash.line,
+            hash.col,
+        );
+        tok.has_space = hash.has_space;
+        tok
+    }
+
+    /// The token spelled by `lhs` followed by `rhs`, which must be a single
+    /// valid one.
+    fn paste(&mut self, lhs: &Token, rhs: &Token) -> Token {
+        let text = format!("{}{}", lhs.val, rhs.val);
+        let tokens = Tokenizer::tokenize(SourceFile::new(&lhs.file.name, &text));
+
+        let mut tok = lhs.clone();
+        match tokens.as_slice() {
+            [pasted, eof] if eof.is(TokenType::TokenEof) && !pasted.is(TokenType::TokenError) => {
+                tok.kind = pasted.kind.clone();
+                tok.val = pasted.val.clone();
+            }
+            _ => {
+                let msg = format!(
+                    "pasting \"{}\" and \"{}\" does not give a valid preprocessing token",
+                    lhs.val, rhs.val
+                );
+                self.error(lhs, msg);
+            }
+        }
+        tok
+    }
+}
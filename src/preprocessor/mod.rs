@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::diagnostic::Diagnostic;
+use crate::preprocessor::builtin::BuiltinMacro;
+use crate::preprocessor::cond::Cond;
+use crate::preprocessor::macros::Macro;
+use crate::tokenizer::source::SourceFile;
+use crate::tokenizer::token::Token;
+use crate::tokenizer::tokentype::TokenType;
+use crate::tokenizer::Tokenizer;
+use crate::util::is_alpha;
+use crate::{CompileOptions, MacroDef};
+
+mod builtin;
+mod cond;
+mod expr;
+mod macros;
+pub mod print;
+
+/// Guards against a header that includes itself without a guard.
+const MAX_INCLUDE_DEPTH: usize = 200;
+
+pub struct Preprocessor {
+    include_paths: Vec<String>,
+    // the predefined macros and the `-D` and `-U` options, read before the
+    // source as `#define` and `#undef` lines
+    prelude: Vec<Rc<SourceFile>>,
+    macros: HashMap<String, Macro>,
+    // the next value of `__COUNTER__`
+    counter: usize,
+    // the `#if` groups being read, innermost last
+    conds: Vec<Cond>,
+    // files whose next lines come from elsewhere after a `#line`
+    // directive, and how their tokens are reported
+    line_markers: Vec<(Rc<SourceFile>, Rc<SourceFile>)>,
+    // tokens still to be processed, the next one last
+    pending: Vec<Token>,
+    // files included and not fully read yet
+    include_depth: usize,
+    pub errors: Vec<Diagnostic>,
+}
+
+impl Preprocessor {
+    pub fn new(opts: &CompileOptions) -> Self {
+        // the command line macros are directives of a file of their own
+        let command_line: String = opts
+            .macros
+            .iter()
+            .map(|def| match def {
+                MacroDef::Define(name, body) => format!("#define {} {}\n", name, body),
+                MacroDef::Undef(name) => format!("#undef {}\n", name),
+            })
+            .collect();
+
+        let prelude = vec![
+            SourceFile::new("<built-in>", &builtin::predefined(opts.source_date_epoch)),
+            SourceFile::new("<command line>", &command_line),
+        ];
+
+        let macros = HashMap::from([
+            ("__FILE__".to_string(), Macro::builtin(BuiltinMacro::File)),
+            ("__LINE__".to_string(), Macro::builtin(BuiltinMacro::Line)),
+            (
+                "__COUNTER__".to_string(),
+                Macro::builtin(BuiltinMacro::Counter),
+            ),
+        ]);
+
+        Self {
+            include_paths: opts.include_paths.clone(),
+            prelude,
+            macros,
+            counter: 0,
+            conds: Vec::new(),
+            line_markers: Vec::new(),
+            pending: Vec::new(),
+            include_depth: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Runs the directives of `file` and expands its macros, the result
+    /// ends with the end of file token.
+    pub fn preprocess(&mut self, file: Rc<SourceFile>) -> Vec<Token> {
+        self.push_tokens(Tokenizer::tokenize(file));
+
+        // read before the file as if it included them
+        for file in self.prelude.clone().into_iter().rev() {
+            self.include_depth += 1;
+            self.push_tokens(Tokenizer::tokenize(file));
+        }
+
+        let mut output = Vec::new();
+        loop {
+            let tok = self.next_token();
+
+            // the end of an included file is where its includer resumes
+            if tok.is(TokenType::TokenEof) {
+                self.close_conds();
+                if self.include_depth == 0 {
+                    output.push(tok);
+                    return output;
+                }
+                self.include_depth -= 1;
+                continue;
+            }
+
+            if tok.is(TokenType::TokenHash) && tok.at_bol {
+                self.directive();
+                continue;
+            }
+
+            if self.expand_macro(&tok) {
+                continue;
+            }
+
+            output.push(tok);
+        }
+    }
+
+    /// The `#define` lines of the macros defined at the end of
+    /// preprocessing, sorted by name. Builtin macros have none.
+    pub fn definitions(&self) -> Vec<String> {
+        let mut names: Vec<&String> = self.macros.keys().collect();
+        names.sort();
+
+        names
+            .into_iter()
+            .filter(|name| self.macros[*name].builtin.is_none())
+            .map(|name| self.macros[name].definition(name))
+            .collect()
+    }
+
+    fn next_token(&mut self) -> Token {
+        let mut tok = self.pending.pop().expect("the token stream ends with EOF");
+
+        let marked = self
+            .line_markers
+            .iter()
+            .find(|(file, _)| Rc::ptr_eq(file, &tok.file));
+        if let Some((_, renamed)) = marked {
+            tok.line = tok.line.saturating_add_signed(renamed.line_delta);
+            tok.file = renamed.clone();
+        }
+        tok
+    }
+
+    /// Makes `tokens` the next ones to be processed.
+    fn push_tokens(&mut self, tokens: Vec<Token>) {
+        self.pending.extend(tokens.into_iter().rev());
+    }
+
+    /// The tokens up to the end of the current line.
+    fn read_line(&mut self) -> Vec<Token> {
+        let mut line = Vec::new();
+        while let Some(tok) = self.pending.last() {
+            if tok.at_bol || tok.is(TokenType::TokenEof) {
+                break;
+            }
+            line.push(self.next_token());
+        }
+
+        line
+    }
+
+    /// Runs the directive after a `#` at the beginning of a line.
+    fn directive(&mut self) {
+        let mut line = self.read_line();
+
+        // a `#` on its own is the null directive
+        if line.is_empty() {
+            return;
+        }
+
+        let name = line.remove(0);
+
+        // the `# 10 "file"` markers of `-E` output
+        if name.is(TokenType::TokenNumber) {
+            line.insert(0, name.clone());
+            return self.line_marker(&name, line);
+        }
+
+        match name.val.as_str() {
+            "include" => self.include(&name, line),
+            "define" => self.define(&name, line),
+            "undef" => self.undef(&name, line),
+            "if" => self.if_directive(&name, line),
+            "ifdef" => self.ifdef(&name, line, true),
+            "ifndef" => self.ifdef(&name, line, false),
+            "elif" => self.elif(&name, line),
+            "else" => self.else_directive(&name),
+            "endif" => self.endif(&name),
+            "line" => {
+                let line = self.expand_all(&line);
+                self.line_marker(&name, line)
+            }
+            _ => {
+                let msg = format!("invalid preprocessing directive #{}", name.val);
+                self.error(&name, msg);
+            }
+        }
+    }
+
+    fn include(&mut self, directive: &Token, line: Vec<Token>) {
+        let (name, quoted) = match Self::header_name(&line) {
+            Some(header) => header,
+            None => {
+                let tok = line.first().unwrap_or(directive);
+                let msg = "#include expects \"FILENAME\" or <FILENAME>".to_string();
+                return self.error(tok, msg);
+            }
+        };
+
+        let path = match self.find_include(&name, quoted, &directive.file) {
+            Some(path) => path,
+            None => return self.error(&line[0], format!("'{}' file not found", name)),
+        };
+
+        if self.include_depth >= MAX_INCLUDE_DEPTH {
+            return self.error(&line[0], "#include nested too deeply".to_string());
+        }
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) => return self.error(&line[0], format!("cannot read {}: {}", path, e)),
+        };
+
+        self.include_depth += 1;
+        self.push_tokens(Tokenizer::tokenize(SourceFile::new(&path, &contents)));
+    }
+
+    /// `#line 10 "file"` says that the next line is line 10 of `file`, the
+    /// current file when there is no name. Flags after the name of a
+    /// linemarker are ignored.
+    fn line_marker(&mut self, directive: &Token, line: Vec<Token>) {
+        let number = match line.first() {
+            Some(tok) => tok,
+            None => {
+                let msg = "#line directive requires a positive integer argument".to_string();
+                return self.error(directive, msg);
+            }
+        };
+        let number: usize = match number.val.parse() {
+            Ok(n) if number.is(TokenType::TokenNumber) && n > 0 => n,
+            _ => {
+                let msg = "#line directive requires a positive integer argument".to_string();
+                return self.error(number, msg);
+            }
+        };
+
+        let name = match line.get(1) {
+            Some(tok) if tok.is(TokenType::TokenString) => {
+                let name = &tok.val[1..tok.val.len() - 1];
+                name.replace("\\\"", "\"").replace("\\\\", "\\")
+            }
+            Some(tok) => {
+                return self.error(tok, "invalid filename for #line directive".to_string())
+            }
+            None => directive.file.name.clone(),
+        };
+
+        // the tokens of the directive are reported already
+        let file = match self
+            .line_markers
+            .iter()
+            .find(|(_, renamed)| Rc::ptr_eq(renamed, &directive.file))
+        {
+            Some((file, _)) => file.clone(),
+            None => directive.file.clone(),
+        };
+        let line = directive.line as isize - directive.file.line_delta;
+        let renamed = file.renamed(&name, number as isize - (line + 1));
+
+        self.line_markers.retain(|(f, _)| !Rc::ptr_eq(f, &file));
+        self.line_markers.push((file, renamed));
+    }
+
+    /// The file name of `"name"` or `<name>`, and whether it is quoted.
+    fn header_name(line: &[Token]) -> Option<(String, bool)> {
+        let first = line.first()?;
+
+        if first.is(TokenType::TokenString) {
+            let name = &first.val[1..first.val.len() - 1];
+            return Some((name.to_string(), true));
+        }
+
+        // the name between angle brackets is made of several tokens
+        if first.is(TokenType::TokenLess) {
+            let end = line.iter().position(|t| t.is(TokenType::TokenGreater))?;
+            let name: String = line[1..end].iter().map(|t| t.val.as_str()).collect();
+            return Some((name, false));
+        }
+
+        None
+    }
+
+    /// Quoted names are looked up next to the including file first, then
+    /// like angle bracket ones in the `-I` paths. The system headers are
+    /// not searched, they declare functions without a body, which the
+    /// parser does not accept yet.
+    fn find_include(&self, name: &str, quoted: bool, from: &SourceFile) -> Option<String> {
+        if Path::new(name).is_absolute() {
+            return Path::new(name).is_file().then(|| name.to_string());
+        }
+
+        let mut dirs: Vec<&Path> = Vec::new();
+        if quoted {
+            dirs.push(Path::new(&from.name).parent().unwrap_or(Path::new("")));
+        }
+        dirs.extend(self.include_paths.iter().map(Path::new));
+
+        dirs.iter()
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+            .map(|path| path.to_string_lossy().into_owned())
+    }
+
+    /// Identifiers and keywords, which can both name a macro.
+    fn is_identifier(tok: &Token) -> bool {
+        !tok.is(TokenType::TokenError) && tok.val.chars().next().is_some_and(is_alpha)
+    }
+
+    fn error(&mut self, tok: &Token, msg: String) {
+        self.errors.push(Diagnostic::error(tok, msg));
+    }
+}
@@ -0,0 +1,96 @@
+use std::rc::Rc;
+
+use crate::tokenizer::source::SourceFile;
+use crate::tokenizer::token::Token;
+use crate::tokenizer::tokentype::TokenType;
+
+/// Blank lines printed as such, more start a new linemarker instead.
+const MAX_BLANK_LINES: usize = 8;
+
+/// The punctuators of C made of several characters.
+static PUNCTUATORS: [&str; 24] = [
+    "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "*=", "/=", "%=", "+=", "-=",
+    "&=", "^=", "|=", "<<=", ">>=", "...", "##", "::",
+];
+
+/// The preprocessed source, `tokens` being written back on the lines they
+/// come from. A `# line "file"` marker tells where the lines come from when
+/// that is not the next line of the same file, so that the text compiles
+/// to the same program and its diagnostics point at the original source.
+///
+/// Tokens from a macro expansion are on the line of the macro invocation.
+pub fn print_tokens(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    let mut file: Option<Rc<SourceFile>> = None;
+    let mut line = 0;
+    let mut prev: Option<&Token> = None;
+
+    for tok in tokens {
+        if tok.is(TokenType::TokenEof) {
+            break;
+        }
+
+        let at = tok.source_token();
+        let same_file = file.as_ref().is_some_and(|f| Rc::ptr_eq(f, &at.file));
+
+        if !same_file || at.line > line {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+
+            match same_file && at.line - line <= MAX_BLANK_LINES {
+                true => out.push_str(&"\n".repeat(at.line - line - 1)),
+                false => {
+                    let name = at.file.name.replace('\\', "\\\\").replace('"', "\\\"");
+                    out.push_str(&format!("# {} \"{}\"\n", at.line, name));
+                }
+            }
+            file = Some(at.file.clone());
+            line = at.line;
+
+            // keeps the indentation of the line
+            out.push_str(&" ".repeat(at.col - 1));
+        } else if tok.has_space || prev.is_some_and(|prev| pastes(prev, tok)) {
+            out.push(' ');
+        }
+
+        out.push_str(&tok.val);
+        prev = Some(tok);
+    }
+
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+/// Whether `prev` directly followed by `tok` would read back as other
+/// tokens, like `-` and `-` as `--`. Only tokens of a macro expansion can
+/// be next to each other without having been read that way.
+///
+/// The output is meant for other compilers too, so this goes by the tokens
+/// of C rather than those `Tokenizer` knows about.
+fn pastes(prev: &Token, tok: &Token) -> bool {
+    if prev.origin.is_none() && tok.origin.is_none() {
+        return false;
+    }
+
+    let (last, first) = match (prev.val.chars().last(), tok.val.chars().next()) {
+        (Some(last), Some(first)) => (last, first),
+        _ => return false,
+    };
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+    match (last, first) {
+        // identifiers and numbers, `1.` and `.5` being numbers too
+        (a, b) if is_word(a) && is_word(b) => true,
+        (a, '.') if a.is_ascii_digit() => true,
+        ('.', b) if b.is_ascii_digit() => true,
+        // comments
+        ('/', '/' | '*') => true,
+        _ => {
+            let joined = format!("{}{}", prev.val, first);
+            PUNCTUATORS.iter().any(|p| p.starts_with(&joined))
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::tokenizer::token::Token;
+
+/// Decodes the contents of a string literal token into bytes, without the
+/// terminating nul.
+pub fn string_bytes(tok: &Token) -> Vec<u8> {
+    let raw: Vec<char> = tok.val.chars().collect();
+    decode(&raw[1..raw.len() - 1])
+}
+
+/// An integer constant, `unsigned` and `long` tell which suffixes it has.
+/// Octal and hexadecimal constants are not `decimal`, they may take an
+/// unsigned type without the `u` suffix.
+pub struct IntLiteral {
+    pub value: u64,
+    pub unsigned: bool,
+    pub long: bool,
+    pub decimal: bool,
+}
+
+/// Splits an integer constant like `42`, `0x1f`, `017` or `1UL` into its
+/// value and suffixes. `u` and `l` or `ll` may come in either order and case,
+/// but the two letters of `ll` have the same case.
+pub fn int_value(tok: &Token) -> Result<IntLiteral, String> {
+    let (radix, body) = match tok.val.get(..2) {
+        Some("0x" | "0X") => (16, &tok.val[2..]),
+        _ if tok.val.starts_with('0') => (8, tok.val.as_str()),
+        _ => (10, tok.val.as_str()),
+    };
+    let digits = body
+        .find(|ch: char| !ch.is_ascii_alphanumeric() || !ch.is_digit(radix.max(10)))
+        .unwrap_or(body.len());
+    let (value, suffix) = body.split_at(digits);
+    if value.is_empty() {
+        return Err(format!(
+            "invalid suffix '{}' on integer constant",
+            &tok.val[1..]
+        ));
+    }
+
+    let (unsigned, long) = match suffix.to_ascii_lowercase().as_str() {
+        _ if suffix.contains("lL") || suffix.contains("Ll") => (None, None),
+        "" => (Some(false), Some(false)),
+        "u" => (Some(true), Some(false)),
+        "l" | "ll" => (Some(false), Some(true)),
+        "ul" | "ull" | "lu" | "llu" => (Some(true), Some(true)),
+        _ => (None, None),
+    };
+    let (unsigned, long) = match (unsigned, long) {
+        (Some(unsigned), Some(long)) => (unsigned, long),
+        _ => return Err(format!("invalid suffix '{}' on integer constant", suffix)),
+    };
+
+    if let Some(digit) = value.chars().find(|ch| !ch.is_digit(radix)) {
+        return Err(format!("invalid digit '{}' in octal constant", digit));
+    }
+
+    match u64::from_str_radix(value, radix) {
+        Ok(value) => Ok(IntLiteral {
+            value,
+            unsigned,
+            long,
+            decimal: radix == 10,
+        }),
+        Err(_) => Err("integer constant is too large for any integer type".to_string()),
+    }
+}
+
+/// The value of a character constant, which is an int holding a char.
+pub fn char_value(tok: &Token) -> i64 {
+    let raw: Vec<char> = tok.val.chars().collect();
+    let bytes = decode(&raw[1..raw.len() - 1]);
+    bytes.first().map(|b| *b as i8 as i64).unwrap_or(0)
+}
+
+fn decode(raw: &[char]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut i = 0;
+
+    while i < raw.len() {
+        if raw[i] != '\\' || i + 1 >= raw.len() {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(raw[i].encode_utf8(&mut buf).as_bytes());
+            i += 1;
+            continue;
+        }
+
+        i += 1; // skip the backslash
+        let ch = raw[i];
+        i += 1;
+
+        let byte = match ch {
+            'a' => 7,
+            'b' => 8,
+            't' => b'\t',
+            'n' => b'\n',
+            'v' => 11,
+            'f' => 12,
+            'r' => b'\r',
+            // [GNU] \e is the escape character
+            'e' => 27,
+            // octal escapes take at most three digits
+            '0'..='7' => {
+                let mut val = ch.to_digit(8).unwrap();
+                let mut digits = 1;
+                while digits < 3 && i < raw.len() && raw[i].is_digit(8) {
+                    val = val * 8 + raw[i].to_digit(8).unwrap();
+                    digits += 1;
+                    i += 1;
+                }
+                val as u8
+            }
+            // hexadecimal escapes take as many digits as there are
+            'x' => {
+                let mut val: u32 = 0;
+                while i < raw.len() && raw[i].is_ascii_hexdigit() {
+                    val = val.wrapping_mul(16) + raw[i].to_digit(16).unwrap();
+                    i += 1;
+                }
+                val as u8
+            }
+            other => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+                continue;
+            }
+        };
+
+        bytes.push(byte);
+    }
+
+    bytes
+}
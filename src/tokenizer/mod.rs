@@ -1,39 +1,71 @@
+use std::mem;
+use std::rc::Rc;
+
+use crate::tokenizer::source::SourceFile;
 use crate::tokenizer::token::Token;
 use crate::tokenizer::tokentype::TokenType::*;
 use crate::tokenizer::tokentype::{kw_type_from_str, TokenType};
 use crate::util::{is_alpha, is_alpha_num, is_digit};
 
+pub mod literal;
+pub mod source;
 pub mod token;
 pub mod tokentype;
 
 pub struct Tokenizer {
+    file: Rc<SourceFile>,
     len: usize,
     start: usize,
     pub input: Vec<char>,
     current: usize,
     line: usize,
     col: usize,
+    start_line: usize,
+    start_col: usize,
+    at_bol: bool,
+    has_space: bool,
 }
 
 impl Tokenizer {
-    pub fn new(input: &str) -> Self {
-        let data: Vec<char> = input.chars().collect();
+    pub fn new(file: Rc<SourceFile>) -> Self {
+        let data: Vec<char> = file.contents.chars().collect();
         let length = data.len();
 
         Self {
+            file,
             input: data,
             current: 0,
-            line: 0,
-            col: 0,
+            line: 1,
+            col: 1,
             len: length,
             start: 0,
+            start_line: 1,
+            start_col: 1,
+            at_bol: true,
+            has_space: false,
+        }
+    }
+
+    /// All the tokens of a file, ending with the end of file token.
+    pub fn tokenize(file: Rc<SourceFile>) -> Vec<Token> {
+        let mut tokenizer = Tokenizer::new(file);
+        let mut tokens = Vec::new();
+
+        loop {
+            let tok = tokenizer.scan_next();
+            let eof = tok.is(TokenEof);
+            tokens.push(tok);
+
+            if eof {
+                return tokens;
+            }
         }
     }
 
     fn make_token(&mut self, t: TokenType) -> Token {
         // println!("col:{}, curr:{}, start:{}", self.col, self.current, self.start);
         let str = self.fetch(self.start, self.current);
-        Token::new(t, &str, self.line, self.col)
+        Token::new(t, &str, &self.file, self.start_line, self.start_col)
     }
 
     pub fn incr_curr(&mut self) {
@@ -43,7 +75,7 @@ impl Tokenizer {
 
     pub fn incr_line(&mut self) {
         self.line += 1;
-        self.col = 0
+        self.col = 1
     }
 
     fn peek(&self, pos: Option<usize>) -> Option<char> {
@@ -81,7 +113,7 @@ impl Tokenizer {
     }
 
     fn error_token(&self, msg: &str) -> Token {
-        Token::new(TokenError, msg, self.line, self.col)
+        Token::new(TokenError, msg, &self.file, self.start_line, self.start_col)
     }
 
     fn advance(&mut self) -> char {
@@ -104,28 +136,57 @@ impl Tokenizer {
         true
     }
 
-    fn skip_whitespaces(&mut self) {
+    /// Skips whitespace and comments, false when a block comment is not
+    /// closed before the end of the input. The comment is then where the
+    /// next token starts.
+    fn skip_whitespaces(&mut self) -> bool {
         loop {
             let ch = match self.peek1() {
-                None => return,
+                None => return true,
                 Some(c) => c,
             };
 
             match ch {
-                ' ' | '\r' | '\t' => {
+                ' ' | '\r' | '\t' | '\x0b' | '\x0c' => {
                     self.advance();
                 }
                 '\n' => {
+                    self.advance();
                     self.incr_line();
+                    self.at_bol = true;
+                }
+                // a backslash newline joins two lines
+                '\\' if self.peek_is('\n', Some(1)) => {
                     self.advance();
+                    self.advance();
+                    self.incr_line();
                 }
                 '/' if self.peek_is('/', Some(1)) => {
                     while !self.peek1_is('\n') && !self.is_end() {
                         self.advance();
                     }
                 }
-                _ => return,
+                '/' if self.peek_is('*', Some(1)) => {
+                    let (line, col) = (self.line, self.col);
+                    self.advance();
+                    self.advance();
+                    while !(self.peek1_is('*') && self.peek_is('/', Some(1))) {
+                        if self.is_end() {
+                            self.start_line = line;
+                            self.start_col = col;
+                            return false;
+                        }
+                        if self.advance() == '\n' {
+                            self.incr_line();
+                        }
+                    }
+                    self.advance();
+                    self.advance();
+                }
+                _ => return true,
             }
+
+            self.has_space = true;
         }
     }
 
@@ -142,24 +203,44 @@ impl Tokenizer {
             }
         }
 
+        // a suffix like `u` or `L`, checked by the parser
+        while self.peek1_is_match(is_alpha_num) {
+            self.advance();
+        }
+
         self.make_token(TokenNumber)
     }
 
     fn string(&mut self) -> Token {
-        while !self.is_end() && !self.peek1_is('"') {
-            if self.peek1_is('\n') {
-                self.incr_line();
-            }
-            self.advance();
+        if !self.quoted('"') {
+            return self.error_token("unterminated string");
         }
 
-        if self.is_end() {
-            return self.error_token("unterminated string.");
+        self.make_token(TokenString)
+    }
+
+    fn char_literal(&mut self) -> Token {
+        if !self.quoted('\'') {
+            return self.error_token("unterminated char literal");
         }
 
-        self.advance();
+        if self.current - self.start <= 2 {
+            return self.error_token("empty char literal");
+        }
 
-        self.make_token(TokenString)
+        self.make_token(TokenCharLiteral)
+    }
+
+    /// Consumes the rest of a literal up to and including the closing quote,
+    /// skipping escaped characters. Literals end at the end of the line.
+    fn quoted(&mut self, quote: char) -> bool {
+        while !self.is_end() && !self.peek1_is(quote) && !self.peek1_is('\n') {
+            if self.advance() == '\\' && !self.is_end() && !self.peek1_is('\n') {
+                self.advance();
+            }
+        }
+
+        self.next_matches(quote)
     }
 
     fn ident(&mut self) -> Token {
@@ -171,10 +252,24 @@ impl Tokenizer {
         self.make_token(ident_type)
     }
 
+    /// The next token, flagged with the whitespace that comes before it.
     pub fn scan_next(&mut self) -> Token {
-        self.skip_whitespaces();
+        let mut tok = self.scan_token();
+        tok.at_bol = mem::replace(&mut self.at_bol, false);
+        tok.has_space = mem::replace(&mut self.has_space, false);
+
+        tok
+    }
+
+    fn scan_token(&mut self) -> Token {
+        if !self.skip_whitespaces() {
+            return self.error_token("unterminated comment");
+        }
 
         self.start = self.current;
+        self.start_line = self.line;
+        self.start_col = self.col;
+
         if self.is_end() {
             return self.make_token(TokenEof);
         }
@@ -189,16 +284,93 @@ impl Tokenizer {
         }
 
         match ch {
+            '"' => self.string(),
+            '\'' => self.char_literal(),
             '(' => self.make_token(TokenLeftParen),
             ')' => self.make_token(TokenRightParen),
             '{' => self.make_token(TokenLeftBrace),
             '}' => self.make_token(TokenRightBrace),
+            '[' => self.make_token(TokenLeftBracket),
+            ']' => self.make_token(TokenRightBracket),
             ';' => self.make_token(TokenSemicolon),
-            '-' => self.make_token(TokenMinus),
-            '+' => self.make_token(TokenPlus),
-            '/' => self.make_token(TokenSlash),
-            '*' => self.make_token(TokenStar),
-            '&' => self.make_token(TokenAddr),
+            ',' => self.make_token(TokenComma),
+            ':' => self.make_token(TokenColon),
+            '?' => self.make_token(TokenQuestion),
+            '.' if self.peek1_is('.') && self.peek_is('.', Some(1)) => {
+                self.advance();
+                self.advance();
+                self.make_token(TokenEllipsis)
+            }
+            '.' => self.make_token(TokenDot),
+            '-' => {
+                let tok_type = match self.next_matches('>') {
+                    true => TokenArrow,
+                    false if self.next_matches('-') => TokenMinusMinus,
+                    false if self.next_matches('=') => TokenMinusEqual,
+                    false => TokenMinus,
+                };
+                self.make_token(tok_type)
+            }
+            '+' => {
+                let tok_type = match self.next_matches('+') {
+                    true => TokenPlusPlus,
+                    false if self.next_matches('=') => TokenPlusEqual,
+                    false => TokenPlus,
+                };
+                self.make_token(tok_type)
+            }
+            '/' => {
+                let tok_type = match self.next_matches('=') {
+                    true => TokenSlashEqual,
+                    false => TokenSlash,
+                };
+                self.make_token(tok_type)
+            }
+            '*' => {
+                let tok_type = match self.next_matches('=') {
+                    true => TokenStarEqual,
+                    false => TokenStar,
+                };
+                self.make_token(tok_type)
+            }
+            '%' => {
+                let tok_type = match self.next_matches('=') {
+                    true => TokenPercentEqual,
+                    false => TokenPercent,
+                };
+                self.make_token(tok_type)
+            }
+            '&' => {
+                let tok_type = match self.next_matches('&') {
+                    true => TokenAndAnd,
+                    false if self.next_matches('=') => TokenAndEqual,
+                    false => TokenAddr,
+                };
+                self.make_token(tok_type)
+            }
+            '|' => {
+                let tok_type = match self.next_matches('|') {
+                    true => TokenPipePipe,
+                    false if self.next_matches('=') => TokenPipeEqual,
+                    false => TokenPipe,
+                };
+                self.make_token(tok_type)
+            }
+            '^' => {
+                let tok_type = match self.next_matches('=') {
+                    true => TokenCaretEqual,
+                    false => TokenCaret,
+                };
+                self.make_token(tok_type)
+            }
+            '~' => self.make_token(TokenTilde),
+            '#' => {
+                let tok_type = match self.next_matches('#') {
+                    true => TokenHashHash,
+                    false => TokenHash,
+                };
+                self.make_token(tok_type)
+            }
             '!' => {
                 let tok_type = match self.next_matches('=') {
                     true => TokenBangEqual,
@@ -214,20 +386,24 @@ impl Tokenizer {
                 self.make_token(tok_type)
             }
             '<' => {
-                let tok_type = match self.next_matches('=') {
-                    true => TokenLessEqual,
+                let tok_type = match self.next_matches('<') {
+                    true if self.next_matches('=') => TokenLessLessEqual,
+                    true => TokenLessLess,
+                    false if self.next_matches('=') => TokenLessEqual,
                     false => TokenLess,
                 };
                 self.make_token(tok_type)
             }
             '>' => {
-                let tok_type = match self.next_matches('=') {
-                    true => TokenGreaterEqual,
+                let tok_type = match self.next_matches('>') {
+                    true if self.next_matches('=') => TokenGreaterGreaterEqual,
+                    true => TokenGreaterGreater,
+                    false if self.next_matches('=') => TokenGreaterEqual,
                     false => TokenGreater,
                 };
                 self.make_token(tok_type)
             }
-            _ => self.error_token(format!("unexpected character {ch}").as_ref()),
+            _ => self.error_token(format!("unexpected character '{ch}'").as_ref()),
         }
     }
 
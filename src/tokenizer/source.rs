@@ -0,0 +1,46 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::rc::Rc;
+
+/// The text of a source file, shared by the tokens read from it.
+pub struct SourceFile {
+    pub name: String,
+    pub contents: Rc<str>,
+    // added to the line numbers of the text, which a `#line` directive
+    // says comes from another line
+    pub line_delta: isize,
+}
+
+impl SourceFile {
+    pub fn new(name: &str, contents: &str) -> Rc<Self> {
+        Rc::new(Self {
+            name: name.to_string(),
+            contents: contents.into(),
+            line_delta: 0,
+        })
+    }
+
+    /// The same text, its lines being reported as those of `name` from
+    /// `line_delta` lines further on.
+    pub fn renamed(&self, name: &str, line_delta: isize) -> Rc<Self> {
+        Rc::new(Self {
+            name: name.to_string(),
+            contents: self.contents.clone(),
+            line_delta,
+        })
+    }
+
+    /// The text of a 1-based line, as reported, without its line
+    /// terminator.
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let index = (line as isize - self.line_delta).checked_sub(1)?;
+        self.contents.lines().nth(usize::try_from(index).ok()?)
+    }
+}
+
+// the contents would bury everything else in a token dump
+impl fmt::Debug for SourceFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "SourceFile({})", self.name)
+    }
+}
@@ -1,23 +1,39 @@
 use std::fmt;
 use std::fmt::Formatter;
+use std::rc::Rc;
 
+use crate::tokenizer::source::SourceFile;
 use crate::tokenizer::tokentype::TokenType;
 
 #[derive(Debug, Clone)]
 pub struct Token {
     pub kind: TokenType,
     pub val: String,
+    pub file: Rc<SourceFile>,
     pub line: usize,
     pub col: usize,
+    // first token of its line, a `#` there starts a preprocessor directive
+    pub at_bol: bool,
+    // preceded by whitespace or a comment
+    pub has_space: bool,
+    // the macros this token comes from, which must not expand it again
+    pub hideset: Vec<String>,
+    // the macro name this token was expanded from
+    pub origin: Option<Rc<Token>>,
 }
 
 impl Token {
-    pub fn new(kind: TokenType, val: &str, line: usize, col: usize) -> Self {
+    pub fn new(kind: TokenType, val: &str, file: &Rc<SourceFile>, line: usize, col: usize) -> Self {
         Self {
             kind,
             col,
             line,
+            file: file.clone(),
             val: val.into(),
+            at_bol: false,
+            has_space: false,
+            hideset: Vec::new(),
+            origin: None,
         }
     }
 
@@ -28,14 +44,24 @@ impl Token {
     pub fn update_kind(&mut self, kind: TokenType) {
         self.kind = kind
     }
+
+    /// The token of the source that this one was expanded from, outside of
+    /// any macro, the token itself when it is not from an expansion.
+    pub fn source_token(&self) -> &Token {
+        let mut tok = self;
+        while let Some(origin) = &tok.origin {
+            tok = origin;
+        }
+        tok
+    }
 }
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "[{}:{}] {:?} {}",
-            self.line, self.col, self.kind, self.val
+            "[{}:{}:{}] {:?} {}",
+            self.file.name, self.line, self.col, self.kind, self.val
         )
     }
 }
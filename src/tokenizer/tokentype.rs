@@ -1,3 +1,6 @@
+use std::fmt;
+use std::fmt::Formatter;
+
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub enum TokenType {
     // Single-character tokens.
@@ -5,15 +8,24 @@ pub enum TokenType {
     TokenRightParen,
     TokenLeftBrace,
     TokenRightBrace,
+    TokenLeftBracket,
+    TokenRightBracket,
     TokenComma,
+    TokenColon,
+    TokenQuestion,
     TokenDot,
     TokenMinus,
     TokenPlus,
     TokenSemicolon,
     TokenSlash,
     TokenStar,
+    TokenPercent,
     TokenAddr,
+    TokenPipe,
+    TokenCaret,
+    TokenTilde,
     TokenDeref,
+    TokenHash,
 
     // One or two character tokens.
     TokenBang,
@@ -24,24 +36,60 @@ pub enum TokenType {
     TokenGreaterEqual,
     TokenLess,
     TokenLessEqual,
+    TokenLessLess,
+    TokenGreaterGreater,
+    TokenArrow,
+    TokenAndAnd,
+    TokenPipePipe,
+    TokenHashHash,
+    TokenEllipsis,
+    TokenPlusPlus,
+    TokenMinusMinus,
+    TokenPlusEqual,
+    TokenMinusEqual,
+    TokenStarEqual,
+    TokenSlashEqual,
+    TokenPercentEqual,
+    TokenAndEqual,
+    TokenPipeEqual,
+    TokenCaretEqual,
+    TokenLessLessEqual,
+    TokenGreaterGreaterEqual,
 
     // Literals.
     TokenIdentifier,
     TokenString,
+    TokenCharLiteral,
     TokenNumber,
 
     // Keywords.
+    TokenBreak,
+    TokenCase,
+    TokenContinue,
+    TokenDefault,
+    TokenDo,
     TokenElse,
     TokenFor,
     TokenFunc,
+    TokenGoto,
     TokenIf,
     TokenNil,
     TokenReturn,
+    TokenSizeof,
+    TokenStatic,
+    TokenStruct,
+    TokenSwitch,
     TokenTrue,
+    TokenUnion,
     TokenVar,
     TokenWhile,
 
+    TokenChar,
+    TokenShort,
     TokenInt,
+    TokenLong,
+    TokenSigned,
+    TokenUnsigned,
 
     TokenError,
     TokenEof,
@@ -51,19 +99,155 @@ impl TokenType {
     pub(crate) fn is(&self, rhs: &TokenType) -> bool {
         self == rhs
     }
+
+    pub(crate) fn is_type_name(&self) -> bool {
+        matches!(
+            self,
+            TokenType::TokenChar
+                | TokenType::TokenShort
+                | TokenType::TokenInt
+                | TokenType::TokenLong
+                | TokenType::TokenSigned
+                | TokenType::TokenUnsigned
+                | TokenType::TokenStruct
+                | TokenType::TokenUnion
+        )
+    }
+
+    /// The binary operator a compound assignment like `+=` applies.
+    pub(crate) fn compound_op(&self) -> Option<TokenType> {
+        match self {
+            TokenType::TokenPlusEqual => Some(TokenType::TokenPlus),
+            TokenType::TokenMinusEqual => Some(TokenType::TokenMinus),
+            TokenType::TokenStarEqual => Some(TokenType::TokenStar),
+            TokenType::TokenSlashEqual => Some(TokenType::TokenSlash),
+            TokenType::TokenPercentEqual => Some(TokenType::TokenPercent),
+            TokenType::TokenAndEqual => Some(TokenType::TokenAddr),
+            TokenType::TokenPipeEqual => Some(TokenType::TokenPipe),
+            TokenType::TokenCaretEqual => Some(TokenType::TokenCaret),
+            TokenType::TokenLessLessEqual => Some(TokenType::TokenLessLess),
+            TokenType::TokenGreaterGreaterEqual => Some(TokenType::TokenGreaterGreater),
+            _ => None,
+        }
+    }
 }
 
 pub fn kw_type_from_str(token_type: &str) -> TokenType {
     match token_type {
+        "break" => TokenType::TokenBreak,
+        "case" => TokenType::TokenCase,
+        "continue" => TokenType::TokenContinue,
+        "default" => TokenType::TokenDefault,
+        "do" => TokenType::TokenDo,
         "else" => TokenType::TokenElse,
+        "goto" => TokenType::TokenGoto,
         "if" => TokenType::TokenIf,
         "nil" => TokenType::TokenNil,
         "return" => TokenType::TokenReturn,
+        "sizeof" => TokenType::TokenSizeof,
+        "static" => TokenType::TokenStatic,
+        "struct" => TokenType::TokenStruct,
+        "switch" => TokenType::TokenSwitch,
+        "union" => TokenType::TokenUnion,
         "var" => TokenType::TokenVar,
         "while" => TokenType::TokenWhile,
         "for" => TokenType::TokenFor,
         "true" => TokenType::TokenTrue,
+        "char" => TokenType::TokenChar,
+        "short" => TokenType::TokenShort,
         "int" => TokenType::TokenInt,
+        "long" => TokenType::TokenLong,
+        "signed" => TokenType::TokenSigned,
+        "unsigned" => TokenType::TokenUnsigned,
         _ => TokenType::TokenIdentifier,
     }
 }
+
+/// How a token kind is named in diagnostics.
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenType::TokenLeftParen => write!(f, "'('"),
+            TokenType::TokenRightParen => write!(f, "')'"),
+            TokenType::TokenLeftBrace => write!(f, "'{{'"),
+            TokenType::TokenRightBrace => write!(f, "'}}'"),
+            TokenType::TokenLeftBracket => write!(f, "'['"),
+            TokenType::TokenRightBracket => write!(f, "']'"),
+            TokenType::TokenComma => write!(f, "','"),
+            TokenType::TokenColon => write!(f, "':'"),
+            TokenType::TokenQuestion => write!(f, "'?'"),
+            TokenType::TokenDot => write!(f, "'.'"),
+            TokenType::TokenMinus => write!(f, "'-'"),
+            TokenType::TokenPlus => write!(f, "'+'"),
+            TokenType::TokenSemicolon => write!(f, "';'"),
+            TokenType::TokenSlash => write!(f, "'/'"),
+            TokenType::TokenStar => write!(f, "'*'"),
+            TokenType::TokenPercent => write!(f, "'%'"),
+            TokenType::TokenAddr => write!(f, "'&'"),
+            TokenType::TokenPipe => write!(f, "'|'"),
+            TokenType::TokenCaret => write!(f, "'^'"),
+            TokenType::TokenTilde => write!(f, "'~'"),
+            TokenType::TokenDeref => write!(f, "'*'"),
+            TokenType::TokenHash => write!(f, "'#'"),
+            TokenType::TokenBang => write!(f, "'!'"),
+            TokenType::TokenBangEqual => write!(f, "'!='"),
+            TokenType::TokenEqual => write!(f, "'='"),
+            TokenType::TokenEqualEqual => write!(f, "'=='"),
+            TokenType::TokenGreater => write!(f, "'>'"),
+            TokenType::TokenGreaterEqual => write!(f, "'>='"),
+            TokenType::TokenLess => write!(f, "'<'"),
+            TokenType::TokenLessEqual => write!(f, "'<='"),
+            TokenType::TokenLessLess => write!(f, "'<<'"),
+            TokenType::TokenGreaterGreater => write!(f, "'>>'"),
+            TokenType::TokenArrow => write!(f, "'->'"),
+            TokenType::TokenAndAnd => write!(f, "'&&'"),
+            TokenType::TokenPipePipe => write!(f, "'||'"),
+            TokenType::TokenHashHash => write!(f, "'##'"),
+            TokenType::TokenEllipsis => write!(f, "'...'"),
+            TokenType::TokenPlusPlus => write!(f, "'++'"),
+            TokenType::TokenMinusMinus => write!(f, "'--'"),
+            TokenType::TokenPlusEqual => write!(f, "'+='"),
+            TokenType::TokenMinusEqual => write!(f, "'-='"),
+            TokenType::TokenStarEqual => write!(f, "'*='"),
+            TokenType::TokenSlashEqual => write!(f, "'/='"),
+            TokenType::TokenPercentEqual => write!(f, "'%='"),
+            TokenType::TokenAndEqual => write!(f, "'&='"),
+            TokenType::TokenPipeEqual => write!(f, "'|='"),
+            TokenType::TokenCaretEqual => write!(f, "'^='"),
+            TokenType::TokenLessLessEqual => write!(f, "'<<='"),
+            TokenType::TokenGreaterGreaterEqual => write!(f, "'>>='"),
+            TokenType::TokenIdentifier => write!(f, "identifier"),
+            TokenType::TokenString => write!(f, "string literal"),
+            TokenType::TokenCharLiteral => write!(f, "character constant"),
+            TokenType::TokenNumber => write!(f, "number"),
+            TokenType::TokenBreak => write!(f, "'break'"),
+            TokenType::TokenCase => write!(f, "'case'"),
+            TokenType::TokenContinue => write!(f, "'continue'"),
+            TokenType::TokenDefault => write!(f, "'default'"),
+            TokenType::TokenDo => write!(f, "'do'"),
+            TokenType::TokenElse => write!(f, "'else'"),
+            TokenType::TokenFor => write!(f, "'for'"),
+            TokenType::TokenFunc => write!(f, "'func'"),
+            TokenType::TokenGoto => write!(f, "'goto'"),
+            TokenType::TokenIf => write!(f, "'if'"),
+            TokenType::TokenNil => write!(f, "'nil'"),
+            TokenType::TokenReturn => write!(f, "'return'"),
+            TokenType::TokenSizeof => write!(f, "'sizeof'"),
+            TokenType::TokenStatic => write!(f, "'static'"),
+            TokenType::TokenStruct => write!(f, "'struct'"),
+            TokenType::TokenSwitch => write!(f, "'switch'"),
+            TokenType::TokenTrue => write!(f, "'true'"),
+            TokenType::TokenUnion => write!(f, "'union'"),
+            TokenType::TokenVar => write!(f, "'var'"),
+            TokenType::TokenWhile => write!(f, "'while'"),
+            TokenType::TokenChar => write!(f, "'char'"),
+            TokenType::TokenShort => write!(f, "'short'"),
+            TokenType::TokenInt => write!(f, "'int'"),
+            TokenType::TokenLong => write!(f, "'long'"),
+            TokenType::TokenSigned => write!(f, "'signed'"),
+            TokenType::TokenUnsigned => write!(f, "'unsigned'"),
+            TokenType::TokenError => write!(f, "invalid token"),
+            TokenType::TokenEof => write!(f, "end of file"),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::Formatter;
+use std::rc::Rc;
+
+use crate::types::Type;
+use crate::util::checked_align_to;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AggregateKind {
+    Struct,
+    Union,
+}
+
+#[derive(Clone, Debug)]
+pub struct Member {
+    pub name: String,
+    pub ty: Type,
+    pub offset: i32,
+}
+
+struct Layout {
+    members: Vec<Member>,
+    size: i32,
+    align: i32,
+    complete: bool,
+}
+
+/// A struct or union type. Copies share one definition, so a tag that is
+/// used before its members are known, e.g. by a pointer to itself, is
+/// completed in place.
+#[derive(Clone)]
+pub struct Aggregate {
+    kind: AggregateKind,
+    tag: Option<String>,
+    layout: Rc<RefCell<Layout>>,
+}
+
+impl Aggregate {
+    /// An incomplete struct or union, see `define`.
+    pub fn new(kind: AggregateKind, tag: Option<String>) -> Self {
+        Self {
+            kind,
+            tag,
+            layout: Rc::new(RefCell::new(Layout {
+                members: Vec::new(),
+                size: 0,
+                align: 1,
+                complete: false,
+            })),
+        }
+    }
+
+    pub fn kind(&self) -> AggregateKind {
+        self.kind
+    }
+
+    /// Lays out the members in declaration order. Struct members are
+    /// padded to their alignment, union members all start at offset 0.
+    /// The size is padded to the alignment of the whole type. Returns false
+    /// when the size does not fit in an `int`, the type is then left empty.
+    pub fn define(&self, members: Vec<(String, Type)>) -> bool {
+        let mut offset = 0;
+        let mut size = 0;
+        let mut align = 1;
+        let mut laid_out = Vec::new();
+
+        for (name, ty) in members {
+            let start = match self.kind {
+                AggregateKind::Struct => checked_align_to(offset, ty.align()),
+                AggregateKind::Union => Some(0),
+            };
+            let end = match start.and_then(|start| start.checked_add(ty.size())) {
+                Some(end) => end,
+                None => return self.define_empty(),
+            };
+
+            if self.kind == AggregateKind::Struct {
+                offset = end;
+            }
+            size = size.max(end);
+            align = align.max(ty.align());
+            laid_out.push(Member {
+                name,
+                offset: start.unwrap_or(0),
+                ty,
+            });
+        }
+
+        let size = match checked_align_to(size, align) {
+            Some(size) => size,
+            None => return self.define_empty(),
+        };
+
+        let mut layout = self.layout.borrow_mut();
+        layout.members = laid_out;
+        layout.size = size;
+        layout.align = align;
+        layout.complete = true;
+
+        true
+    }
+
+    /// Completes a type that is too large without members, so that it is
+    /// not reported as incomplete as well.
+    fn define_empty(&self) -> bool {
+        let mut layout = self.layout.borrow_mut();
+        layout.complete = true;
+
+        false
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.layout.borrow().complete
+    }
+
+    pub fn size(&self) -> i32 {
+        self.layout.borrow().size
+    }
+
+    pub fn align(&self) -> i32 {
+        self.layout.borrow().align
+    }
+
+    pub fn member(&self, name: &str) -> Option<Member> {
+        let layout = self.layout.borrow();
+        layout.members.iter().find(|m| m.name == name).cloned()
+    }
+}
+
+/// Two aggregates are the same type only if they share a definition.
+impl PartialEq for Aggregate {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.layout, &other.layout)
+    }
+}
+
+// members may refer back to the aggregate itself, print the name only
+impl fmt::Debug for Aggregate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            AggregateKind::Struct => "struct",
+            AggregateKind::Union => "union",
+        };
+
+        match &self.tag {
+            Some(tag) => write!(f, "{} {}", kind, tag),
+            None => write!(f, "{} (anonymous)", kind),
+        }
+    }
+}
@@ -0,0 +1,665 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{
+    ArithExpr, Assign, Ast, Cast, FunctionCall, GlobalInit, GlobalVar, LiteralKind, MemberAccess,
+    StatementsKind, Switch, Ternary, Unary, Variable,
+};
+use crate::diagnostic::Diagnostic;
+use crate::parser::localscope::LocalScope;
+use crate::tokenizer::token::Token;
+use crate::tokenizer::tokentype::TokenType;
+use crate::types::Type;
+
+pub struct TypeChecker {
+    pub scope: LocalScope,
+    pub functions: HashMap<String, Type>,
+    pub globals: HashMap<String, Type>,
+    pub errors: Vec<Diagnostic>,
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        Self {
+            scope: LocalScope::new(),
+            functions: HashMap::new(),
+            globals: HashMap::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn check(&mut self, node: &mut Ast) {
+        if let Ast::Unary(u) = node {
+            if u.token.is(TokenType::TokenSizeof) {
+                return self.sizeof(node);
+            }
+        }
+
+        match node {
+            Ast::Statements(stmts) => {
+                if let StatementsKind::Program = stmts.kind {
+                    self.declare_globals(&stmts.asts);
+                }
+
+                for stmt in stmts.asts.iter_mut() {
+                    self.check(stmt);
+                }
+            }
+            Ast::Function(f) => {
+                self.scope = std::mem::replace(&mut f.scope, LocalScope::new());
+                self.check(&mut f.body);
+                f.scope = std::mem::replace(&mut self.scope, LocalScope::new());
+            }
+            Ast::GlobalVar(g) => self.global_var(g),
+            Ast::Literal(lit) => {
+                if lit.ty.is_some() {
+                    return;
+                }
+
+                lit.ty = match &lit.kind {
+                    LiteralKind::String(bytes) => Some(Type::array_of(Type::Char, bytes.len() + 1)),
+                    _ if lit.val.parse::<i32>().is_ok() => Some(Type::Int),
+                    _ => Some(Type::Long),
+                };
+            }
+            Ast::Variable(v) => self.variable(v),
+            Ast::Assign(a) => self.assign(a),
+            Ast::Declaration(d) => {
+                for init in d.inits.iter_mut() {
+                    self.check(init);
+                }
+            }
+            Ast::Unary(u) => self.unary(u),
+            Ast::ArithExpr(arith) => self.arithmetic(arith),
+            Ast::Ternary(t) => self.ternary(t),
+            Ast::Cast(c) => self.cast(c),
+            Ast::MemberAccess(m) => self.member_access(m),
+            Ast::FunctionCall(f) => self.func_call(f),
+            Ast::If(i) => {
+                self.check(&mut i.cond);
+                self.check(&mut i.then);
+                if let Some(alt) = &mut i.alt {
+                    self.check(alt);
+                }
+            }
+            Ast::Loop(l) => {
+                for clause in [&mut l.init, &mut l.cond, &mut l.incr]
+                    .into_iter()
+                    .flatten()
+                {
+                    self.check(clause);
+                }
+                self.check(&mut l.then);
+            }
+            Ast::Switch(s) => self.switch(s),
+            Ast::Case(c) => self.check(&mut c.stmt),
+            Ast::Jump(_) => {}
+            Ast::Label(l) => self.check(&mut l.stmt),
+            Ast::Return(r) => {
+                if let Some(value) = &mut r.value {
+                    self.check(value);
+                }
+            }
+        }
+    }
+
+    fn declare_globals(&mut self, asts: &[Ast]) {
+        for ast in asts {
+            match ast {
+                Ast::Function(f) => {
+                    self.functions.insert(f.name.clone(), f.ty.clone());
+                }
+                Ast::GlobalVar(g) => {
+                    self.globals.insert(g.name.clone(), g.ty.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Folds the initializer of a global into the constant data emitted for it.
+    fn global_var(&mut self, g: &mut GlobalVar) {
+        // string literal data is known from the start
+        if g.init.is_empty() {
+            return;
+        }
+
+        let elem = g.ty.element().clone();
+        if let Type::Struct(agg) = &elem {
+            self.error(
+                &g.token,
+                format!(
+                    "initializer of '{}' of type {} is not supported",
+                    g.name, agg
+                ),
+            );
+            return;
+        }
+        let count = (g.ty.size() / elem.size()) as usize;
+
+        // a char array can be initialized by a string literal
+        if let (Type::Char, [Ast::Literal(lit)]) = (&elem, g.init.as_slice()) {
+            if let LiteralKind::String(bytes) = &lit.kind {
+                if bytes.len() > count {
+                    self.error(
+                        &g.token,
+                        format!("initializer string for '{}' is too long", g.name),
+                    );
+                }
+                g.data = bytes
+                    .iter()
+                    .take(count)
+                    .map(|b| GlobalInit::Value(*b as i64))
+                    .collect();
+                return;
+            }
+        }
+
+        if g.init.len() > count {
+            self.error(
+                &g.token,
+                format!("excess elements in initializer of '{}'", g.name),
+            );
+        }
+
+        let mut data = Vec::new();
+        for init in g.init.iter_mut() {
+            self.check(init);
+
+            let address = match elem.is_pointer() {
+                true => init.eval_address(),
+                false => None,
+            };
+            let value = match address {
+                Some((label, offset)) => Some(GlobalInit::Address(label, offset)),
+                // the value is stored with the size of the element
+                None => init
+                    .eval_const()
+                    .map(|v| GlobalInit::Value(elem.convert(v))),
+            };
+
+            match value {
+                Some(value) => data.push(value),
+                None => self.error(
+                    &g.token,
+                    format!("initializer of '{}' is not a compile-time constant", g.name),
+                ),
+            }
+        }
+
+        g.data = data;
+    }
+
+    fn variable(&mut self, v: &mut Variable) {
+        let ty = match v.is_global {
+            true => self.globals.get(&v.val).cloned(),
+            false => self.scope.type_by_id(v.id).cloned(),
+        };
+        v.ty = Some(ty.unwrap_or(Type::Long));
+    }
+
+    /// Replaces `sizeof expr` with the size of the operand, which is not
+    /// evaluated.
+    fn sizeof(&mut self, node: &mut Ast) {
+        let size = match node {
+            Ast::Unary(u) => {
+                self.check(&mut u.right);
+                let ty = u.right.ty().cloned().unwrap_or(Type::Long);
+                if ty.is_incomplete() {
+                    let msg = format!(
+                        "invalid application of 'sizeof' to an incomplete type '{}'",
+                        ty
+                    );
+                    self.error(&u.token, msg);
+                }
+                ty.size()
+            }
+            _ => 0,
+        };
+
+        // size_t is unsigned long
+        *node = Ast::new_typed_literal(size.to_string(), Type::ULong);
+    }
+
+    /// Folds the case labels into constants of the type of the condition,
+    /// each value may only be used once.
+    fn switch(&mut self, s: &mut Switch) {
+        self.check(&mut s.cond);
+        let ty = s.cond.ty().cloned().unwrap_or(Type::Long);
+        if !ty.is_integer() {
+            let msg = format!(
+                "statement requires expression of integer type ({} invalid)",
+                ty
+            );
+            self.error(&s.token, msg);
+        }
+
+        // narrower conditions are promoted to int
+        let ty = ty.promoted();
+        Self::convert(&mut s.cond, &ty, &s.token);
+
+        let mut seen = HashSet::new();
+        for case in s.cases.iter_mut() {
+            let expr = match &mut case.expr {
+                Some(expr) => expr,
+                None => continue,
+            };
+
+            self.check(expr);
+            let value = match expr.eval_const() {
+                Some(value) => value,
+                None => {
+                    let msg = "expression is not an integer constant expression".to_string();
+                    self.error(&case.token, msg);
+                    continue;
+                }
+            };
+
+            case.value = ty.convert(value);
+            if !seen.insert(case.value) {
+                self.error(
+                    &case.token,
+                    format!("duplicate case value '{}'", case.value),
+                );
+            }
+        }
+
+        self.check(&mut s.body);
+    }
+
+    fn assign(&mut self, a: &mut Assign) {
+        self.check(&mut a.left);
+        self.check(&mut a.right);
+
+        let ty = a.left.ty().cloned().unwrap_or(Type::Long);
+        let right = a.right.ty().cloned().unwrap_or(Type::Long);
+        if !Self::is_lvalue(&a.left) {
+            self.error(&a.token, "expression is not assignable".to_string());
+        } else if let Type::Array(_, _) = ty {
+            self.error(&a.token, format!("array type {} is not assignable", ty));
+        } else if let Some(op) = a.token.kind.compound_op() {
+            // the result is stored back to the left operand, `p += n` keeps
+            // a pointer but `n += p` or `p -= q` would not
+            let right = right.decay();
+            let valid = !right.is_pointer() && Self::binary_type(&op, &ty, &right).is_some();
+            if valid {
+                self.pointee_size(&a.token, &ty);
+            }
+            if valid && ty.is_integer() {
+                // the operation is done in the common type of both sides,
+                // the left operand is converted when it is loaded
+                let common = match op {
+                    TokenType::TokenLessLess | TokenType::TokenGreaterGreater => right.promoted(),
+                    _ => Self::arith_type(&ty, &right),
+                };
+                Self::convert(&mut a.right, &common, &a.token);
+            } else if !valid {
+                let msg = match a.token.val.as_str() {
+                    // ++x and --x are x += 1 and x -= 1
+                    "++" | "--" => format!("invalid operand to unary {} ({})", a.token.val, ty),
+                    _ => format!(
+                        "invalid operands to binary {} ({} and {})",
+                        a.token.val, ty, right
+                    ),
+                };
+                self.error(&a.token, msg);
+            }
+        } else if matches!(ty, Type::Struct(_)) || matches!(right, Type::Struct(_)) {
+            // structs are copied, which needs the same type on both sides
+            if ty != right {
+                let msg = format!("assigning to {} from incompatible type {}", ty, right);
+                self.error(&a.token, msg);
+            }
+        } else if Self::is_scalar(&right.decay()) {
+            Self::convert(&mut a.right, &ty, &a.token);
+        }
+
+        a.ty = Some(ty);
+    }
+
+    fn unary(&mut self, u: &mut Unary) {
+        self.check(&mut u.right);
+        let operand = u.right.ty().cloned().unwrap_or(Type::Long);
+
+        let ty = match u.token.kind {
+            TokenType::TokenMinus | TokenType::TokenPlus | TokenType::TokenTilde => {
+                if !operand.is_integer() {
+                    self.error(
+                        &u.token,
+                        format!("invalid operand to unary {} ({})", u.operator, operand),
+                    );
+                    Type::Long
+                } else {
+                    let ty = operand.promoted();
+                    Self::convert(&mut u.right, &ty, &u.token);
+                    ty
+                }
+            }
+            TokenType::TokenBang => {
+                if !Self::is_scalar(&operand.decay()) {
+                    self.error(
+                        &u.token,
+                        format!("invalid operand to unary {} ({})", u.operator, operand),
+                    );
+                }
+                Type::Int
+            }
+            TokenType::TokenAddr => {
+                if !Self::is_lvalue(&u.right) {
+                    self.error(&u.token, "cannot take the address of an rvalue".to_string());
+                }
+                Type::pointer_to(operand)
+            }
+            TokenType::TokenPlusPlus | TokenType::TokenMinusMinus => {
+                if !Self::is_lvalue(&u.right) {
+                    self.error(&u.token, "expression is not assignable".to_string());
+                } else if !Self::is_scalar(&operand) {
+                    self.error(
+                        &u.token,
+                        format!("invalid operand to unary {} ({})", u.operator, operand),
+                    );
+                } else {
+                    self.pointee_size(&u.token, &operand);
+                }
+                operand
+            }
+            TokenType::TokenDeref => match operand.base() {
+                Some(base) => base.clone(),
+                None => {
+                    self.error(
+                        &u.token,
+                        format!("invalid pointer dereference of type {}", operand),
+                    );
+                    Type::Long
+                }
+            },
+            _ => operand,
+        };
+
+        u.ty = Some(ty);
+    }
+
+    fn arithmetic(&mut self, arith: &mut ArithExpr) {
+        self.check(&mut arith.left);
+        self.check(&mut arith.right);
+
+        let left = arith.left.ty().map(|ty| ty.decay()).unwrap_or(Type::Long);
+        let right = arith.right.ty().map(|ty| ty.decay()).unwrap_or(Type::Long);
+
+        // the left operand of a comma is only evaluated for its side effects
+        if arith.token.is(TokenType::TokenComma) {
+            arith.ty = Some(right);
+            return;
+        }
+
+        match Self::binary_type(&arith.token.kind, &left, &right) {
+            Some(ty) => {
+                if left.is_integer() && right.is_integer() {
+                    self.convert_operands(arith, &left, &right);
+                }
+                if matches!(
+                    arith.token.kind,
+                    TokenType::TokenPlus | TokenType::TokenMinus
+                ) {
+                    let pointer = if left.is_pointer() { &left } else { &right };
+                    self.pointee_size(&arith.token, pointer);
+                }
+                arith.ty = Some(ty);
+            }
+            None => {
+                self.error(
+                    &arith.token,
+                    format!(
+                        "invalid operands to binary {} ({} and {})",
+                        arith.operator, left, right
+                    ),
+                );
+                arith.ty = Some(Type::Long);
+            }
+        }
+    }
+
+    /// Pointer arithmetic is scaled by the size of the pointee, which has to
+    /// be known. Other types are not checked.
+    fn pointee_size(&mut self, tok: &Token, ty: &Type) {
+        if let Some(base) = ty.base() {
+            if base.is_incomplete() {
+                let msg = format!("arithmetic on a pointer to an incomplete type '{}'", base);
+                self.error(tok, msg);
+            }
+        }
+    }
+
+    /// Applies the usual arithmetic conversions to the integer operands of a
+    /// binary operator. Both sides of a shift are only promoted, and the
+    /// operands of `&&` and `||` are only compared to zero.
+    fn convert_operands(&mut self, arith: &mut ArithExpr, left: &Type, right: &Type) {
+        match arith.token.kind {
+            TokenType::TokenAndAnd | TokenType::TokenPipePipe => {}
+            TokenType::TokenLessLess | TokenType::TokenGreaterGreater => {
+                Self::convert(&mut arith.left, &left.promoted(), &arith.token);
+                Self::convert(&mut arith.right, &right.promoted(), &arith.token);
+            }
+            _ => {
+                let ty = Self::arith_type(left, right);
+                Self::convert(&mut arith.left, &ty, &arith.token);
+                Self::convert(&mut arith.right, &ty, &arith.token);
+            }
+        }
+    }
+
+    /// The result type of the binary operator `op` on operands of the
+    /// decayed types `left` and `right`, `None` when they are invalid.
+    fn binary_type(op: &TokenType, left: &Type, right: &Type) -> Option<Type> {
+        match op {
+            _ if !Self::is_scalar(left) || !Self::is_scalar(right) => None,
+            TokenType::TokenPlus => match (left.is_pointer(), right.is_pointer()) {
+                (false, false) => Some(Self::arith_type(left, right)),
+                (true, false) => Some(left.clone()),
+                (false, true) => Some(right.clone()),
+                (true, true) => None,
+            },
+            TokenType::TokenMinus => match (left.is_pointer(), right.is_pointer()) {
+                (false, false) => Some(Self::arith_type(left, right)),
+                (true, false) => Some(left.clone()),
+                (true, true) => Some(Type::Long),
+                (false, true) => None,
+            },
+            TokenType::TokenStar
+            | TokenType::TokenSlash
+            | TokenType::TokenPercent
+            | TokenType::TokenAddr
+            | TokenType::TokenCaret
+            | TokenType::TokenPipe => match left.is_integer() && right.is_integer() {
+                true => Some(Self::arith_type(left, right)),
+                false => None,
+            },
+            // the type of a shift is that of its left operand
+            TokenType::TokenLessLess | TokenType::TokenGreaterGreater => {
+                match left.is_integer() && right.is_integer() {
+                    true => Some(left.promoted()),
+                    false => None,
+                }
+            }
+            _ => Some(Type::Int),
+        }
+    }
+
+    /// The operands are both arithmetic, pointers where the other may also
+    /// be an integer like `0`, or structs of the same type.
+    fn ternary(&mut self, t: &mut Ternary) {
+        self.check(&mut t.cond);
+        self.check(&mut t.then);
+        self.check(&mut t.alt);
+
+        let cond = t.cond.ty().map(|ty| ty.decay()).unwrap_or(Type::Long);
+        if !Self::is_scalar(&cond) {
+            let msg = format!(
+                "used type {} where arithmetic or pointer type is required",
+                cond
+            );
+            self.error(&t.token, msg);
+        }
+
+        let then = t.then.ty().map(|ty| ty.decay()).unwrap_or(Type::Long);
+        let alt = t.alt.ty().map(|ty| ty.decay()).unwrap_or(Type::Long);
+        let ty = match (&then, &alt) {
+            (l, r) if l.is_integer() && r.is_integer() => {
+                let ty = Self::arith_type(l, r);
+                Self::convert(&mut t.then, &ty, &t.token);
+                Self::convert(&mut t.alt, &ty, &t.token);
+                ty
+            }
+            (l, r) if l.is_pointer() && (r.is_pointer() || r.is_integer()) => then.clone(),
+            (l, r) if l.is_integer() && r.is_pointer() => alt.clone(),
+            (l, r) if l == r => then.clone(),
+            _ => {
+                let msg = format!("incompatible operand types ({} and {})", then, alt);
+                self.error(&t.token, msg);
+                Type::Long
+            }
+        };
+
+        t.ty = Some(ty);
+    }
+
+    /// Casts are between integers and pointers, a pointer converted to a
+    /// narrower integer keeps its low bits.
+    fn cast(&mut self, c: &mut Cast) {
+        self.check(&mut c.expr);
+        let from = c.expr.ty().map(|ty| ty.decay()).unwrap_or(Type::Long);
+
+        if !Self::is_scalar(&c.ty) {
+            let msg = format!(
+                "used type {} where arithmetic or pointer type is required",
+                c.ty
+            );
+            self.error(&c.token, msg);
+        } else if !Self::is_scalar(&from) {
+            let msg = format!(
+                "operand of type {} where arithmetic or pointer type is required",
+                from
+            );
+            self.error(&c.token, msg);
+        }
+    }
+
+    fn member_access(&mut self, m: &mut MemberAccess) {
+        self.check(&mut m.left);
+        let left = m.left.ty().cloned().unwrap_or(Type::Long);
+
+        // struct values only live in memory, the member is read through
+        // the address of the struct
+        let member = match &left {
+            Type::Struct(_) if !Self::is_lvalue(&m.left) => {
+                let msg = format!("member access on a {} rvalue is not supported", left);
+                self.error(&m.token, msg);
+                m.ty = Some(Type::Long);
+                return;
+            }
+            Type::Struct(agg) => agg.member(m.name.as_str()),
+            _ => {
+                let msg = format!(
+                    "member reference base type {} is not a structure or union",
+                    left
+                );
+                self.error(&m.token, msg);
+                m.ty = Some(Type::Long);
+                return;
+            }
+        };
+
+        match member {
+            Some(member) => {
+                m.offset = member.offset;
+                m.ty = Some(member.ty);
+            }
+            None => {
+                self.error(
+                    &m.token,
+                    format!("no member named '{}' in {}", m.name, left),
+                );
+                m.ty = Some(Type::Long);
+            }
+        }
+    }
+
+    fn func_call(&mut self, f: &mut FunctionCall) {
+        for arg in f.args.iter_mut() {
+            self.check(arg);
+            if let Some(ty @ Type::Struct(_)) = arg.ty() {
+                let msg = format!("passing {} by value is not supported", ty);
+                self.error(&f.token, msg);
+            }
+        }
+
+        // calls to functions defined elsewhere are implicitly declared as returning int
+        let ty = match self.functions.get(&f.name).cloned() {
+            Some(Type::Function(ret, params)) => {
+                if params.len() != f.args.len() {
+                    let msg = format!(
+                        "function {} expects {} arguments, {} given",
+                        f.name,
+                        params.len(),
+                        f.args.len()
+                    );
+                    self.error(&f.token, msg);
+                }
+
+                // arguments are converted as if assigned to the parameters
+                for (arg, param) in f.args.iter_mut().zip(params.iter()) {
+                    let valid = arg.ty().is_some_and(|ty| Self::is_scalar(&ty.decay()));
+                    if valid && Self::is_scalar(param) {
+                        Self::convert(arg, param, &f.token);
+                    }
+                }
+                *ret
+            }
+            _ => Type::Int,
+        };
+
+        f.ty = Some(ty);
+    }
+
+    fn is_lvalue(node: &Ast) -> bool {
+        match node {
+            Ast::Variable(_) => true,
+            Ast::Unary(u) => u.token.is(TokenType::TokenDeref),
+            Ast::MemberAccess(m) => Self::is_lvalue(&m.left),
+            _ => false,
+        }
+    }
+
+    /// Integers and pointers, the operands of arithmetic and comparisons.
+    fn is_scalar(ty: &Type) -> bool {
+        ty.is_integer() || ty.is_pointer()
+    }
+
+    /// The common type of two integers by the usual arithmetic conversions:
+    /// both are promoted, then the narrower one is converted to the wider
+    /// one, and to the unsigned one when they have the same size.
+    fn arith_type(left: &Type, right: &Type) -> Type {
+        let (left, right) = (left.promoted(), right.promoted());
+        match left.size().cmp(&right.size()) {
+            Ordering::Greater => left,
+            Ordering::Less => right,
+            Ordering::Equal if right.is_unsigned() => right,
+            Ordering::Equal => left,
+        }
+    }
+
+    /// Wraps `node` in a conversion to `ty` unless it already has that type.
+    /// `token` is the operator the conversion is done for.
+    fn convert(node: &mut Ast, ty: &Type, token: &Token) {
+        if node.ty() == Some(ty) {
+            return;
+        }
+
+        let expr = std::mem::replace(node, Ast::new_block(Vec::new()));
+        *node = Ast::new_cast(token.clone(), expr, ty.clone());
+    }
+
+    fn error(&mut self, tok: &Token, msg: String) {
+        self.errors.push(Diagnostic::error(tok, msg));
+    }
+}
@@ -0,0 +1,168 @@
+use std::fmt;
+use std::fmt::Formatter;
+
+use crate::types::aggregate::Aggregate;
+
+pub mod aggregate;
+pub mod checker;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Char,
+    Short,
+    Int,
+    Long,
+    UChar,
+    UShort,
+    UInt,
+    ULong,
+    Pointer(Box<Type>),
+    Array(Box<Type>, usize),
+    Function(Box<Type>, Vec<Type>),
+    Struct(Aggregate),
+}
+
+impl Type {
+    pub fn pointer_to(base: Type) -> Type {
+        Type::Pointer(Box::new(base))
+    }
+
+    pub fn array_of(base: Type, len: usize) -> Type {
+        Type::Array(Box::new(base), len)
+    }
+
+    pub fn func_type(ret: Type, params: Vec<Type>) -> Type {
+        Type::Function(Box::new(ret), params)
+    }
+
+    pub fn size(&self) -> i32 {
+        match self {
+            Type::Char | Type::UChar => 1,
+            Type::Short | Type::UShort => 2,
+            Type::Int | Type::UInt => 4,
+            Type::Long | Type::ULong | Type::Pointer(_) => 8,
+            Type::Array(base, len) => base.size() * (*len as i32),
+            Type::Function(_, _) => 1,
+            Type::Struct(agg) => agg.size(),
+        }
+    }
+
+    pub fn align(&self) -> i32 {
+        match self {
+            Type::Array(base, _) => base.align(),
+            Type::Struct(agg) => agg.align(),
+            _ => self.size(),
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            Type::Char
+                | Type::Short
+                | Type::Int
+                | Type::Long
+                | Type::UChar
+                | Type::UShort
+                | Type::UInt
+                | Type::ULong
+        )
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        matches!(self, Type::UChar | Type::UShort | Type::UInt | Type::ULong)
+    }
+
+    /// The unsigned variant of an integer type.
+    pub fn to_unsigned(&self) -> Type {
+        match self {
+            Type::Char => Type::UChar,
+            Type::Short => Type::UShort,
+            Type::Int => Type::UInt,
+            Type::Long => Type::ULong,
+            _ => self.clone(),
+        }
+    }
+
+    /// The integer promotion: types smaller than `int` are operated on as
+    /// `int`.
+    pub fn promoted(&self) -> Type {
+        match self {
+            Type::Char | Type::Short | Type::UChar | Type::UShort => Type::Int,
+            _ => self.clone(),
+        }
+    }
+
+    /// The value `val` converted to this type, extended back to 64 bits the
+    /// way it is held in a register.
+    pub fn convert(&self, val: i64) -> i64 {
+        match self {
+            Type::Char => val as i8 as i64,
+            Type::Short => val as i16 as i64,
+            Type::Int => val as i32 as i64,
+            Type::UChar => val as u8 as i64,
+            Type::UShort => val as u16 as i64,
+            Type::UInt => val as u32 as i64,
+            _ => val,
+        }
+    }
+
+    /// Pointers and arrays, i.e. the types that can be dereferenced.
+    pub fn is_pointer(&self) -> bool {
+        self.base().is_some()
+    }
+
+    pub fn base(&self) -> Option<&Type> {
+        match self {
+            Type::Pointer(base) | Type::Array(base, _) => Some(base),
+            _ => None,
+        }
+    }
+
+    /// The innermost element type of an array, the type itself otherwise.
+    pub fn element(&self) -> &Type {
+        match self {
+            Type::Array(base, _) => base.element(),
+            _ => self,
+        }
+    }
+
+    /// Structs and unions whose members are not known yet, and arrays of them.
+    pub fn is_incomplete(&self) -> bool {
+        match self.element() {
+            Type::Struct(agg) => !agg.is_complete(),
+            _ => false,
+        }
+    }
+
+    /// Arrays and functions used as values decay to pointers.
+    pub fn decay(&self) -> Type {
+        match self {
+            Type::Array(base, _) => Type::pointer_to(*base.clone()),
+            Type::Function(_, _) => Type::pointer_to(self.clone()),
+            _ => self.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Char => write!(f, "char"),
+            Type::Short => write!(f, "short"),
+            Type::Int => write!(f, "int"),
+            Type::Long => write!(f, "long"),
+            Type::UChar => write!(f, "unsigned char"),
+            Type::UShort => write!(f, "unsigned short"),
+            Type::UInt => write!(f, "unsigned int"),
+            Type::ULong => write!(f, "unsigned long"),
+            Type::Pointer(base) => write!(f, "{} *", base),
+            Type::Array(base, len) => write!(f, "{} [{}]", base, len),
+            Type::Function(ret, params) => {
+                let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+                write!(f, "{} ({})", ret, params.join(", "))
+            }
+            Type::Struct(agg) => write!(f, "{}", agg),
+        }
+    }
+}
@@ -3,17 +3,22 @@ pub fn is_digit(ch: char) -> bool {
 }
 
 pub fn is_alpha(ch: char) -> bool {
-    return ch.is_alphabetic();
+    return ch.is_alphabetic() || ch == '_';
 }
 
 pub fn is_alpha_num(ch: char) -> bool {
-    return ch.is_alphanumeric();
+    return ch.is_alphanumeric() || ch == '_';
 }
 
 pub fn align_to(offset: i32, align: i32) -> i32 {
     (offset + align - 1) / align * align
 }
 
+/// `align_to`, `None` when the aligned offset does not fit in an `i32`.
+pub fn checked_align_to(offset: i32, align: i32) -> Option<i32> {
+    offset.checked_add(align - 1).map(|n| n / align * align)
+}
+
 pub fn x86_program_offset(offset: i32) -> String {
     let aligned = align_to(offset, 16);
     append_str("$", aligned, "")
@@ -1,11 +1,25 @@
 #!/bin/bash
+cat <<EOF | gcc -xc -c -o tmp2.o -
+int ret3() { return 3; }
+int ret5() { return 5; }
+int add(int x, int y) { return x+y; }
+int sub(int x, int y) { return x-y; }
+
+int add6(int a, int b, int c, int d, int e, int f) {
+  return a+b+c+d+e+f;
+}
+
+int add8(int a, int b, int c, int d, int e, int f, int g, int h) {
+  return a+b+c+d+e+f+g+h;
+}
+EOF
 
 assert() {
   expected="$1"
   input="$2"
 
-  ./chibicc "$input" || exit
-  gcc -static -m64 -o tmp tmp.s
+  echo "$input" | ./chibicc -o tmp.s - || exit
+  gcc -static -m64 -o tmp tmp.s tmp2.o
   ./tmp
   actual="$?"
 
@@ -17,72 +31,818 @@ assert() {
   fi
 }
 
-# assert 0 '{ return 0; }'
-# assert 42 '{ return 42; }'
-# assert 21 '{ return 5+20-4; }'
-# assert 41 '{ return  12 + 34 - 5 ; }'
-# assert 47 '{ return 5+6*7; }'
-# assert 15 '{ return 5*(9-6); }'
-# assert 4 '{ return (3+5)/2; }'
-# assert 10 '{ return -10+20; }'
-# assert 10 '{ return - -10; }'
-# assert 10 '{ return - - +10; }'
-
-# assert 0 '{ return 0==1; }'
-# assert 1 '{ return 42==42; }'
-# assert 1 '{ return 0!=1; }'
-# assert 0 '{ return 42!=42; }'
-
-# assert 1 '{ return 0<1; }'
-# assert 0 '{ return 1<1; }'
-# assert 0 '{ return 2<1; }'
-# assert 1 '{ return 0<=1; }'
-# assert 1 '{ return 1<=1; }'
-# assert 0 '{ return 2<=1; }'
-
-# assert 1 '{ return 1>0; }'
-# assert 0 '{ return 1>1; }'
-# assert 0 '{ return 1>2; }'
-# assert 1 '{ return 1>=0; }'
-# assert 1 '{ return 1>=1; }'
-# assert 0 '{ return 1>=2; }'
-
-# assert 3 '{ a=3; return a; }'
-# assert 8 '{ a=3; z=5; return a+z; }'
-
-# assert 3 '{ a=3; return a; }'
-# assert 8 '{ a=3; z=5; return a+z; }'
-# assert 6 '{ a=3; return a+3; }'
-# assert 3 '{ foo=3; return foo; }'
-# assert 8 '{ foo123=3; bar=5; return foo123+bar; }'
-
-# assert 3 '{ 1; 2; return 3; }'
-# assert 1 '{ return 1; 2; 3; }'
-# assert 2 '{ 1; return 2; 3; }'
-
-# assert 2 '{ ; return 2;} }'
-# assert 3 '{ {1; {2;} return 3;} }'
-
-# assert 5 '{ ;;; return 5; }'
-
-# assert 3 '{ if (0) return 2; return 3; }'
-# assert 3 '{ if (1-1) return 2; return 3; }'
-# assert 2 '{ if (1) return 2; return 3; }'
-# assert 2 '{ if (2-1) return 2; return 3; }'
-# assert 4 '{ if (0) { 1; 2; return 3; } else { return 4; } }'
-# assert 3 '{ if (1) { 1; 2; return 3; } else { return 4; } }'
-
-# assert 55 '{ i=0; j=0; for (i=0; i<=10; i=i+1) j=i+j; return j; }'
-# assert 3 '{ for (;;) {return 3;} return 5; }'
-
-# assert 10 '{ i=0; while(i<10) { i=i+1; } return i; }'
-
-# assert 3 '{ x=3; return *&x; }'
-assert 3 '{ x=3; y=&x; z=&y; return **z; }'
-# assert 5 '{ x=3; y=5; return *(&x+8); }'
-# assert 3 '{ x=3; y=5; return *(&y-8); }'
-# assert 5 '{ x=3; y=&x; *y=5; return x; }'
-# assert 7 '{ x=3; y=5; *(&x+8)=7; return y; }'
-# assert 7 '{ x=3; y=5; *(&y-8)=7; return x; }'
+assert_error() {
+  expected="$1"
+  input="$2"
+
+  actual=$(echo "$input" | ./chibicc -o tmp.s - 2>&1)
+  if [ "$?" != 0 ] && echo "$actual" | grep -qF -- "$expected"; then
+    echo "$input => $expected"
+  else
+    echo "$input => error '$expected' expected, but got '$actual'"
+    exit 1
+  fi
+}
+
+assert 0 'int main() { return 0; }'
+assert 42 'int main() { return 42; }'
+assert 21 'int main() { return 5+20-4; }'
+assert 41 'int main() { return  12 + 34 - 5 ; }'
+assert 47 'int main() { return 5+6*7; }'
+assert 15 'int main() { return 5*(9-6); }'
+assert 4 'int main() { return (3+5)/2; }'
+assert 10 'int main() { return -10+20; }'
+assert 10 'int main() { return - -10; }'
+assert 10 'int main() { return - - +10; }'
+
+assert 0 'int main() { return 0==1; }'
+assert 1 'int main() { return 42==42; }'
+assert 1 'int main() { return 0!=1; }'
+assert 0 'int main() { return 42!=42; }'
+
+assert 1 'int main() { return 0<1; }'
+assert 0 'int main() { return 1<1; }'
+assert 0 'int main() { return 2<1; }'
+assert 1 'int main() { return 0<=1; }'
+assert 1 'int main() { return 1<=1; }'
+assert 0 'int main() { return 2<=1; }'
+
+assert 1 'int main() { return 1>0; }'
+assert 0 'int main() { return 1>1; }'
+assert 0 'int main() { return 1>2; }'
+assert 1 'int main() { return 1>=0; }'
+assert 1 'int main() { return 1>=1; }'
+assert 0 'int main() { return 1>=2; }'
+
+assert 9 'int main() { return 10-2+1; }'
+assert 2 'int main() { return 16/4/2; }'
+assert 3 'int main() { return 1+2*3%4; }'
+assert 1 'int main() { return 7%3; }'
+assert 255 'int main() { return -7%3; }'
+assert 0 'int main() { return !1; }'
+assert 1 'int main() { return !0; }'
+assert 1 'int main() { return !!42; }'
+assert 3 'int main() { return ~-4; }'
+assert 1 'int main() { return 5&3; }'
+assert 7 'int main() { return 5|3; }'
+assert 6 'int main() { return 5^3; }'
+assert 7 'int main() { return 1|2^4&6; }'
+assert 40 'int main() { return 5<<3; }'
+assert 5 'int main() { return 40>>3; }'
+assert 255 'int main() { return -8>>3; }'
+assert 1 'int main() { return 1<<2==4; }'
+assert 1 'int main() { return 2&&3; }'
+assert 0 'int main() { return 2&&0; }'
+assert 1 'int main() { return 0||3; }'
+assert 0 'int main() { return 0||0; }'
+assert 1 'int main() { return 1||0&&0; }'
+assert 3 'int main() { int x=3; 0&&(x=5); return x; }'
+assert 3 'int main() { int x=3; 1||(x=5); return x; }'
+assert 5 'int main() { int x=3; 1&&(x=5); return x; }'
+assert 10 'int g = 1 << 3 | 5 % 3; int main() { return g; }'
+assert 1 'int g = 0 || 2 && !0; int main() { return g; }'
+
+assert 3 'int main() { int a; a=3; return a; }'
+assert 3 'int main() { int a=3; return a; }'
+assert 8 'int main() { int a=3; int z=5; return a+z; }'
+assert 49 "int main() { $(for i in $(seq 0 199); do printf 'int x%d=%d; ' $i $i; done)return x199-x150; }"
+assert 6 'int main() { int a, b; a=b=3; return a+b; }'
+assert 3 'int main() { int foo=3; return foo; }'
+assert 8 'int main() { int foo123=3; int bar=5; return foo123+bar; }'
+
+assert 3 'int main() { 1; 2; return 3; }'
+assert 1 'int main() { return 1; 2; 3; }'
+assert 2 'int main() { 1; return 2; 3; }'
+
+assert 2 'int main() { ; return 2; }'
+assert 3 'int main() { {1; {2;} return 3;} }'
+
+assert 5 'int main() { ;;; return 5; }'
+
+assert 3 'int main() { if (0) return 2; return 3; }'
+assert 3 'int main() { if (1-1) return 2; return 3; }'
+assert 2 'int main() { if (1) return 2; return 3; }'
+assert 2 'int main() { if (2-1) return 2; return 3; }'
+assert 4 'int main() { if (0) { 1; 2; return 3; } else { return 4; } }'
+assert 3 'int main() { if (1) { 1; 2; return 3; } else { return 4; } }'
+
+assert 55 'int main() { int i=0; int j=0; for (i=0; i<=10; i=i+1) j=i+j; return j; }'
+assert 3 'int main() { for (;;) {return 3;} return 5; }'
+
+assert 10 'int main() { int i=0; while(i<10) { i=i+1; } return i; }'
+
+assert 3 'int main() { int x=3; { int x=5; } return x; }'
+assert 5 'int main() { int x=3; { x=5; } return x; }'
+assert 7 'int main() { int x=3; { int y=4; x=x+y; } return x; }'
+assert 2 'int main() { int a[10]; int b=2; return b; }'
+
+assert 3 'int main() { return ret3(); }'
+assert 5 'int main() { return ret5(); }'
+assert 8 'int main() { return add(3, 5); }'
+assert 2 'int main() { return sub(5, 3); }'
+assert 21 'int main() { return add6(1,2,3,4,5,6); }'
+assert 66 'int main() { return add6(1,2,add6(3,4,5,6,7,8),9,10,11); }'
+assert 136 'int main() { return add6(1,2,add6(3,add6(4,5,6,7,8,9),10,11,12,13),14,15,16); }'
+assert 36 'int main() { return add8(1,2,3,4,5,6,7,8); }'
+assert 36 'int main() { int x=1; return add8(x,2,3,4,5,6,7,8); }'
+
+assert 32 'int main() { return ret32(); } int ret32() { return 32; }'
+assert 7 'int main() { return add2(3,4); } int add2(int x, int y) { return x+y; }'
+assert 1 'int main() { return sub2(4,3); } int sub2(int x, int y) { return x-y; }'
+assert 55 'int main() { return fib(9); } int fib(int x) { if (x<=1) return 1; return fib(x-1) + fib(x-2); }'
+assert 18 'int main() { return mul_char(7, 3, 3); } int mul_char(char a, char b, char c) { return a*b-c; }'
+assert 4 'int main() { return sub_short(7, 3); } int sub_short(short a, short b) { return a-b; }'
+assert 4 'int main() { return sub_long(7, 3); } int sub_long(long a, long b) { return a-b; }'
+assert 1 'int main() { return trunc(257); } int trunc(char c) { return c; }'
+assert 1 'int main() { return neg(-1) == -1; } long neg(char c) { return c; }'
+assert 36 'int main() { return sum8(1,2,3,4,5,6,7,8); } int sum8(int a, int b, int c, int d, int e, int f, int g, int h) { return a+b+c+d+e+f+g+h; }'
+
+assert 3 'int main() { int x=3; return *&x; }'
+assert 3 'int main() { int x=3; int *y=&x; int **z=&y; return **z; }'
+assert 5 'int main() { long x=3; long y=5; return *(&x+1); }'
+assert 3 'int main() { long x=3; long y=5; return *(&y-1); }'
+assert 5 'int main() { int x=3; int *y=&x; *y=5; return x; }'
+assert 7 'int main() { long x=3; long y=5; *(&x+1)=7; return y; }'
+assert 7 'int main() { long x=3; long y=5; *(&y-1)=7; return x; }'
+assert 2 'int main() { long x; long y; long z; return &z-&x; }'
+assert 2 'int main() { long x, y; return (&y+2)-(&x+1); }'
+assert 5 'int main() { int x=3; int *p=&x; int **pp=&p; **pp=5; return x; }'
+assert 3 'int main() { int x, y; x=y=3; return x; }'
+assert 3 'int main() { int x[2]; int *y=&x; *y=3; return *x; }'
+assert 3 'int main() { int x[3]; *x=3; *(x+1)=4; *(x+2)=5; return *x; }'
+assert 4 'int main() { int x[3]; *x=3; *(x+1)=4; *(x+2)=5; return *(x+1); }'
+assert 5 'int main() { int x[3]; *x=3; *(x+1)=4; *(x+2)=5; return *(x+2); }'
+
+assert 0 'int main() { int x[2][3]; int *y=x; *y=0; return **x; }'
+assert 1 'int main() { int x[2][3]; int *y=x; *(y+1)=1; return *(*x+1); }'
+assert 2 'int main() { int x[2][3]; int *y=x; *(y+2)=2; return *(*x+2); }'
+assert 3 'int main() { int x[2][3]; int *y=x; *(y+3)=3; return **(x+1); }'
+assert 4 'int main() { int x[2][3]; int *y=x; *(y+4)=4; return *(*(x+1)+1); }'
+assert 5 'int main() { int x[2][3]; int *y=x; *(y+5)=5; return *(*(x+1)+2); }'
+
+assert 3 'int main() { int x[3]; *x=3; x[1]=4; x[2]=5; return *x; }'
+assert 4 'int main() { int x[3]; *x=3; x[1]=4; x[2]=5; return *(x+1); }'
+assert 5 'int main() { int x[3]; *x=3; x[1]=4; x[2]=5; return *(x+2); }'
+assert 5 'int main() { int x[3]; *x=3; x[1]=4; 2[x]=5; return *(x+2); }'
+
+assert 0 'int main() { int x[2][3]; int *y=x; y[0]=0; return x[0][0]; }'
+assert 1 'int main() { int x[2][3]; int *y=x; y[1]=1; return x[0][1]; }'
+assert 2 'int main() { int x[2][3]; int *y=x; y[2]=2; return x[0][2]; }'
+assert 3 'int main() { int x[2][3]; int *y=x; y[3]=3; return x[1][0]; }'
+assert 4 'int main() { int x[2][3]; int *y=x; y[4]=4; return x[1][1]; }'
+assert 5 'int main() { int x[2][3]; int *y=x; y[5]=5; return x[1][2]; }'
+
+assert 5 'int main() { int x=3; int y=5; return *(&x+1); }'
+assert 3 'int main() { int x=3; int y=5; return *(&y-1); }'
+assert 7 'int main() { char x=1; char y=2; int z=4; return x+y+z; }'
+assert 1 'int main() { char x[3]; x[0]=-1; x[1]=2; int y=4; return x[1]+3-y; }'
+assert 45 'int main() { int a[10]; int i; for (i=0; i<10; i=i+1) a[i]=i; return sum(a, 10); } int sum(int *a, int n) { int i, s=0; for (i=0; i<n; i=i+1) s=s+a[i]; return s; }'
+
+assert 4 'int main() { int x; return sizeof(x); }'
+assert 4 'int main() { int x; return sizeof x; }'
+assert 8 'int main() { int *x; return sizeof(x); }'
+assert 16 'int main() { int x[4]; return sizeof(x); }'
+assert 48 'int main() { int x[3][4]; return sizeof(x); }'
+assert 16 'int main() { int x[3][4]; return sizeof(*x); }'
+assert 4 'int main() { int x[3][4]; return sizeof(**x); }'
+assert 5 'int main() { int x[3][4]; return sizeof(**x) + 1; }'
+assert 5 'int main() { int x[3][4]; return sizeof **x + 1; }'
+assert 4 'int main() { int x[3][4]; return sizeof(**x + 1); }'
+assert 4 'int main() { int x=1; return sizeof(x=2); }'
+assert 1 'int main() { int x=1; sizeof(x=2); return x; }'
+assert 1 'int main() { return sizeof(char); }'
+assert 2 'int main() { return sizeof(short); }'
+assert 4 'int main() { return sizeof(int); }'
+assert 8 'int main() { return sizeof(long); }'
+assert 8 'int main() { return sizeof(char *); }'
+assert 12 'int main() { return sizeof(int[3]); }'
+assert 24 'int main() { return sizeof(long[3]); }'
+assert 8 'int main() { return sizeof(sizeof(int)); }'
+assert 1 'int main() { return sizeof(int) - 5 > 0; }'
+assert 1 'int main() { int x; return sizeof x - 5 > 0; }'
+assert 1 'int main() { return sizeof(char[2147483647]) == 2147483647; }'
+assert 1 'int main() { return sizeof(signed char); }'
+assert 2 'int main() { return sizeof(short int); }'
+assert 4 'int main() { return sizeof(unsigned); }'
+assert 8 'int main() { return sizeof(unsigned long int); }'
+assert 8 'int main() { return sizeof(long long); }'
+assert 8 'int main() { return sizeof((long)1); }'
+assert 4 'int main() { char c=1; return sizeof(c + c); }'
+assert 4 'int main() { unsigned char c=1; return sizeof(-c); }'
+assert 8 'int main() { int i=1; long l=1; return sizeof(1 ? i : l); }'
+
+assert 44 'int main() { return (char)300; }'
+assert 1 'int main() { return (char)255 == -1; }'
+assert 1 'int main() { return (unsigned char)-1 == 255; }'
+assert 1 'int main() { return (short)65535 == -1; }'
+assert 1 'int main() { return (long)(unsigned)-1 == 4294967295; }'
+assert 1 'int main() { long x=-1; return (unsigned)x == 4294967295; }'
+assert 4 'int main() { int x=3; long a=(long)&x; *(int *)a=4; return x; }'
+assert 1 'int x = (unsigned char)257; int main() { return x; }'
+assert 1 'int main() { return -1 < 1; }'
+assert 0 'int main() { return -1 < (unsigned)1; }'
+assert 1 'int main() { long x=-1; return x < (unsigned)1; }'
+assert 1 'int main() { unsigned long x=-1; return x > 0; }'
+assert 1 'int main() { unsigned x=1; return (x - 2) / 2 == 2147483647; }'
+assert 127 'int main() { unsigned x=-2; return x >> 25; }'
+assert 255 'int main() { int x=-2; return (x >> 25) & 255; }'
+assert 1 'int main() { unsigned a=3; int b=-1; return a * b == 4294967293; }'
+assert 1 'int main() { return 2147483647 + 1 < 0; }'
+assert 1 'int main() { unsigned char c=255; return c + 1 == 256; }'
+assert 1 'int main() { unsigned char c=255; c++; return c == 0; }'
+assert 1 'int main() { char c=127; c += 1; return c == -128; }'
+assert 1 'int main() { unsigned short s=65535; return s == 65535; }'
+assert 1 'int main() { int i=-7; i /= (unsigned)2; return i == 2147483644; }'
+assert 1 'int main() { unsigned a=10; a %= 3; return a; }'
+assert 1 'int main() { return (1 ? (unsigned)1 : -1) > 0; }'
+assert 3 'int main() { unsigned x=4294967295; switch (x) { case -1: return 3; } return 0; }'
+assert 5 'int g(unsigned char c) { return c; } int main() { return g(261); }'
+assert 1 'int x = -1 < (unsigned)1 ? 0 : 1; int main() { return x; }'
+assert 0 'int main() { return -1 < 1u; }'
+assert 4 'int main() { return sizeof(1u); }'
+assert 4 'int main() { return sizeof(4294967295U); }'
+assert 8 'int main() { return sizeof(4294967296u); }'
+assert 8 'int main() { return sizeof(1L) + sizeof(1ll) - sizeof(1LL); }'
+assert 8 'int main() { return sizeof(1ul) + sizeof(1LU) - sizeof(1uLL); }'
+assert 8 'int main() { return sizeof(2147483648); }'
+assert 1 'int main() { return 4294967295u == (unsigned)-1; }'
+assert 1 'int main() { return 18446744073709551615u == -1 && 18446744073709551615u > 0; }'
+assert 7 'int main() { return 7lu; }'
+assert 8 'int main() { return 010; }'
+assert 0 'int main() { return 0; }'
+assert 31 'int main() { return 0x1f; }'
+assert 31 'int main() { return 0X1F; }'
+assert 16 'int main() { return 0x10u; }'
+assert 4 'int main() { return sizeof(0xffffffff); }'
+assert 0 'int main() { return 0xffffffff < 0; }'
+assert 8 'int main() { return sizeof(0x100000000); }'
+assert 8 'int main() { return sizeof(037777777777) + sizeof(0x7fffffff); }'
+assert 3 'int main() { int a[3u]; return sizeof(a) / sizeof(a[0]); }'
+
+assert 0 'int x; int main() { return x; }'
+assert 3 'int x; int main() { x=3; return x; }'
+assert 7 'int x; int y; int main() { x=3; y=4; return x+y; }'
+assert 7 'int x, y; int main() { x=3; y=4; return x+y; }'
+assert 0 'int x[4]; int main() { x[0]=0; x[1]=1; x[2]=2; x[3]=3; return x[0]; }'
+assert 1 'int x[4]; int main() { x[0]=0; x[1]=1; x[2]=2; x[3]=3; return x[1]; }'
+assert 2 'int x[4]; int main() { x[0]=0; x[1]=1; x[2]=2; x[3]=3; return x[2]; }'
+assert 3 'int x[4]; int main() { x[0]=0; x[1]=1; x[2]=2; x[3]=3; return x[3]; }'
+
+assert 4 'int x; int main() { return sizeof(x); }'
+assert 16 'int x[4]; int main() { return sizeof(x); }'
+
+assert 3 'int x=3; int main() { return x; }'
+assert 5 'static int x=2+3; int main() { return x; }'
+assert 1 'char c=-255; int main() { return c; }'
+assert 10 'long l=10; int main() { return l; }'
+assert 6 'int a[3]={1,2,3}; int main() { return a[0]+a[1]+a[2]; }'
+assert 0 'int a[4]={1,2}; int main() { return a[3]; }'
+assert 5 'int a[2][2]={{1,2},{3,4}}; int main() { return a[1][0]+a[0][1]; }'
+assert 3 'int x=3; int *p=&x; int main() { return *p; }'
+assert 3 'int a[3]={1,2,3}; int *p=a+2; int main() { return *p; }'
+assert 2 'int a[3]={1,2,3}; int *p=&a[1]; int main() { return *p; }'
+assert 4 'int x=4; int main() { int x=5; return ret_x(); } int ret_x() { return x; }'
+assert 8 'static int counter; int main() { bump(); bump(); return counter; } static int bump() { counter=counter+4; return 0; }'
+
+assert 0 'int main() { return ""[0]; }'
+assert 1 'int main() { return sizeof(""); }'
+assert 97 'int main() { return "abc"[0]; }'
+assert 99 'int main() { return "abc"[2]; }'
+assert 0 'int main() { return "abc"[3]; }'
+assert 4 'int main() { return sizeof("abc"); }'
+assert 7 'int main() { return "\a"[0]; }'
+assert 8 'int main() { return "\b"[0]; }'
+assert 9 'int main() { return "\t"[0]; }'
+assert 10 'int main() { return "\n"[0]; }'
+assert 27 'int main() { return "\e"[0]; }'
+assert 0 'int main() { return "\0"[0]; }'
+assert 16 'int main() { return "\20"[0]; }'
+assert 65 'int main() { return "\101"[0]; }'
+assert 104 'int main() { return "\1500"[0]; }'
+assert 119 'int main() { return "\x77"[0]; }'
+assert 165 'int main() { char *p="\xA5"; return *p+256; }'
+assert 255 'int main() { char *p="\x00ff"; return *p+256; }'
+assert 7 'int main() { return sizeof("abc" "def"); }'
+assert 100 'int main() { return ("abc" "def")[3]; }'
+assert 98 'char *s="abc"; int main() { return s[1]; }'
+assert 99 'char s[4]="abc"; int main() { return s[2]; }'
+assert 0 'char s[8]="abc"; int main() { return s[5]; }'
+assert 97 "int main() { return 'a'; }"
+assert 10 "int main() { return '\\n'; }"
+assert 4 "int main() { return sizeof('a'); }"
+assert 0 'int main() { printf("hello %d\n", 42); return 0; }'
+
+assert 1 'int main() { struct {int a; int b;} x; x.a=1; x.b=2; return x.a; }'
+assert 2 'int main() { struct {int a; int b;} x; x.a=1; x.b=2; return x.b; }'
+assert 1 'int main() { struct {char a; int b; char c;} x; x.a=1; x.b=2; x.c=3; return x.a; }'
+assert 3 'int main() { struct {char a; int b; char c;} x; x.b=1; x.b=2; x.c=3; return x.c; }'
+assert 0 'int main() { struct {char a; char b;} x[3]; char *p=x; p[0]=0; return x[0].a; }'
+assert 1 'int main() { struct {char a; char b;} x[3]; char *p=x; p[1]=1; return x[0].b; }'
+assert 2 'int main() { struct {char a; char b;} x[3]; char *p=x; p[2]=2; return x[1].a; }'
+assert 6 'int main() { struct {int a[3]; int b[5];} x; int *p=&x; x.a[0]=6; return p[0]; }'
+assert 7 'int main() { struct {int a[3]; int b[5];} x; int *p=&x; x.b[0]=7; return p[3]; }'
+assert 6 'int main() { struct { struct { int b; } a; } x; x.a.b=6; return x.a.b; }'
+assert 4 'int main() { struct {int a;} x; return sizeof(x); }'
+assert 8 'int main() { struct {int a; int b;} x; return sizeof(x); }'
+assert 12 'int main() { struct {int a[3];} x; return sizeof(x); }'
+assert 16 'int main() { struct {int a;} x[4]; return sizeof(x); }'
+assert 24 'int main() { struct {int a[3];} x[2]; return sizeof(x); }'
+assert 2 'int main() { struct {char a; char b;} x; return sizeof(x); }'
+assert 0 'int main() { struct {} x; return sizeof(x); }'
+assert 8 'int main() { struct {char a; int b;} x; return sizeof(x); }'
+assert 16 'int main() { struct {char a; long b;} x; return sizeof(x); }'
+assert 16 'int main() { struct t {int a; int b;} x; struct t y; return sizeof(x)+sizeof(y); }'
+assert 8 'int main() { struct t {int a; int b;}; struct t y; return sizeof(y); }'
+assert 2 'int main() { struct t {char a[2];}; { struct t {char a[4];}; } struct t y; return sizeof(y); }'
+assert 3 'int main() { struct t {int x;}; int t=1; struct t y; y.x=2; return t+y.x; }'
+assert 3 'int main() { struct t {char a;} x; struct t *y=&x; x.a=3; return y->a; }'
+assert 3 'int main() { struct t {char a;} x; struct t *y=&x; y->a=3; return x.a; }'
+assert 5 'int main() { struct node {int val; struct node *next;} a; struct node b; a.next=&b; b.val=5; return a.next->val; }'
+assert 16 'int main() { struct node {int val; struct node *next;} a; return sizeof(a); }'
+assert 7 'int main() { struct fwd *p; struct fwd {int a; int b;} x; p=&x; x.b=7; return p->b; }'
+assert 8 'int main() { union {int a; char b[6];} x; return sizeof(x); }'
+assert 3 'int main() { union {int a; char b[4];} x; x.a=515; return x.b[0]; }'
+assert 2 'int main() { union {int a; char b[4];} x; x.a=515; return x.b[1]; }'
+assert 0 'int main() { union {int a; char b[4];} x; x.a=515; return x.b[2]; }'
+assert 4 'int main() { union {struct {char a; char b;} s; short c;} x; x.c=1027; return x.s.b; }'
+assert 3 'int main() { struct {int a,b;} x,y; x.a=3; y=x; return y.a; }'
+assert 7 'int main() { struct t {int a,b;}; struct t x; x.a=7; struct t y; struct t *z=&y; *z=x; return y.a; }'
+assert 7 'int main() { struct t {int a,b;}; struct t x; x.a=7; struct t y, *p=&x, *q=&y; *q=*p; return y.a; }'
+assert 5 'int main() { struct t {char a, b;} x, y; x.a=5; y=x; return y.a; }'
+assert 3 'int main() { union {int a,b;} x,y; x.a=3; y.a=5; y=x; return y.a; }'
+assert 15 'struct point {int x; int y;}; struct point p; int main() { p.x=5; p.y=10; return p.x+p.y; }'
+assert 10 'struct point {int x; int y;} p, *q=&p; int main() { q->y=10; return p.y; }'
+
+assert 7 'int main() { int i=2; i+=5; return i; }'
+assert 7 'int main() { int i=2; return i+=5; }'
+assert 3 'int main() { int i=5; i-=2; return i; }'
+assert 6 'int main() { int i=3; i*=2; return i; }'
+assert 3 'int main() { int i=6; i/=2; return i; }'
+assert 2 'int main() { int i=8; i%=3; return i; }'
+assert 2 'int main() { int i=6; i&=3; return i; }'
+assert 7 'int main() { int i=6; i|=3; return i; }'
+assert 5 'int main() { int i=6; i^=3; return i; }'
+assert 24 'int main() { int i=3; i<<=3; return i; }'
+assert 3 'int main() { int i=24; i>>=3; return i; }'
+assert 8 'int main() { int i=1; int j=2; i+=j+=5; return i; }'
+assert 128 'int main() { char c=127; c+=1; return -c; }'
+assert 128 'int main() { char c=127; return -(c+=1); }'
+assert 3 'int main() { int i=2; ++i; return i; }'
+assert 3 'int main() { int i=2; return ++i; }'
+assert 1 'int main() { int i=2; return --i; }'
+assert 2 'int main() { int i=2; return i++; }'
+assert 2 'int main() { int i=2; return i--; }'
+assert 3 'int main() { int i=2; i++; return i; }'
+assert 1 'int main() { int i=2; i--; return i; }'
+assert 128 'int main() { char c=127; return -++c; }'
+assert 127 'int main() { char c=127; return c++; }'
+assert 128 'int main() { char c=127; c++; return -c; }'
+assert 5 'int main() { int i=2; return i++ + ++i - 1; }'
+assert 3 'int main() { int a[3]; a[0]=1; a[1]=2; a[2]=3; int *p=a; p+=2; return *p; }'
+assert 2 'int main() { int a[3]; a[0]=1; a[1]=2; a[2]=3; int *p=a+2; p-=1; return *p; }'
+assert 2 'int main() { int a[3]; a[0]=1; a[1]=2; a[2]=3; int *p=a; p++; return *p; }'
+assert 1 'int main() { int a[3]; a[0]=1; a[1]=2; a[2]=3; int *p=a; return *p++; }'
+assert 3 'int main() { int a[3]; a[0]=1; a[1]=2; a[2]=3; int *p=a+1; return *++p; }'
+assert 5 'int main() { int x=3; int *p=&x; *p+=2; return x; }'
+assert 9 'int main() { int a[3]; a[0]=1; a[1]=2; a[2]=3; int i=1; a[i++]+=6; return a[1]+i-1; }'
+assert 4 'int main() { int a[3]; a[0]=1; a[1]=2; a[2]=3; int i=2; a[i]++; return a[2]; }'
+assert 3 'int main() { struct {int x;} s, *p=&s; s.x=4; p->x--; return s.x; }'
+assert 6 'int main() { struct {char c; long l;} s; s.l=3; s.l*=2; return s.l; }'
+assert 10 'int main() { int i, j=0; for (i=0; i<5; i++) j+=i; return j; }'
+
+assert 3 'int main() { int i=0; for (;;i++) if (i==3) break; return i; }'
+assert 4 'int main() { int i=0; while (1) { if (i++==3) break; } return i; }'
+assert 3 'int main() { int i=0; for (;;) { for (;;) break; i++; if (i==3) break; } return i; }'
+assert 10 'int main() { int i=0, j=0; for (; i<10; i++) { if (i>5) continue; j++; } return i+j-6; }'
+assert 5 'int main() { int i=0, j=0; while (i<10) { i++; if (i%2) continue; j++; } return j; }'
+assert 6 'int main() { int i, j, n=0; for (i=0; i<3; i++) for (j=0; j<3; j++) { if (j>i) continue; n++; } return n; }'
+assert 3 'int main() { int i=0; goto a; a: i++; b: i++; c: i++; return i; }'
+assert 2 'int main() { int i=0; goto e; d: i++; e: i++; f: i++; return i; }'
+assert 1 'int main() { int i=0; goto i; g: i++; h: i++; i: i++; return i; }'
+assert 10 'int main() { int i=0; loop: if (i<10) { i++; goto loop; } return i; }'
+assert 7 'int main() { int i=0; do i++; while (i<7); return i; }'
+assert 1 'int main() { int i=0; do { i++; } while (0); return i; }'
+assert 2 'int main() { int i=0, j=0; do { i++; if (i%2) continue; j+=i; } while (i<3); return j+i-3; }'
+assert 3 'int main() { int i=0; do { if (++i==3) break; } while (1); return i; }'
+assert 10 'int main() { int i=0, n=0; do { int j=0; do n++; while (++j<2); } while (++i<5); return n; }'
+assert 5 'int main() { int i=0; switch(0) { case 0:i=5;break; case 1:i=6;break; case 2:i=7;break; } return i; }'
+assert 6 'int main() { int i=0; switch(1) { case 0:i=5;break; case 1:i=6;break; case 2:i=7;break; } return i; }'
+assert 7 'int main() { int i=0; switch(2) { case 0:i=5;break; case 1:i=6;break; case 2:i=7;break; } return i; }'
+assert 0 'int main() { int i=0; switch(3) { case 0:i=5;break; case 1:i=6;break; case 2:i=7;break; } return i; }'
+assert 5 'int main() { int i=0; switch(0) { case 0:i=5;break; default:i=7; } return i; }'
+assert 7 'int main() { int i=0; switch(1) { case 0:i=5;break; default:i=7; } return i; }'
+assert 2 'int main() { int i=0; switch(1) { case 0: 0; case 1: 0; case 2: 0; i=2; } return i; }'
+assert 0 'int main() { int i=0; switch(3) { case 0: 0; case 1: 0; case 2: 0; i=2; } return i; }'
+assert 3 'int main() { int i=0; switch(-1) { case 4294967295: i=3; break; } return i; }'
+assert 12 'int main() { int n=0, i; for (i=0; i<5; i++) switch (i) { case 1: n+=1; case 2: n+=2; break; case 4: n+=7; continue; default: n+=100; } return n-200; }'
+assert 9 'int main() { int i=0, j=0; switch (1) { case 1: switch (2) { case 1: j=1; break; case 2: j=5; break; } i=4; } return i+j; }'
+assert 4 'int main() { int i=0; switch (1) { default: i=9; case 1: i=4; } return i; }'
+assert 8 'int main() { int i=1; switch (i) { case 0: while (i<8) { i++; case 1: i++; } } return i; }'
+assert 20 'int f(int x) { switch (x) { case 10: return 1; case 11: return 2; case 12: return 4; case 13: return 8; case 15: return 16; default: return 0; } } int main() { return f(9)+f(10)+f(12)+f(14)+f(15)+f(16)+f(99)-1; }'
+assert 69 'int f(long x) { switch (x) { case -2: return 1; case -1: return 2; case 0: return 4; case 1: return 8; case 2: return 16; } return 32; } int main() { return f(-3)+f(-2)+f(0)+f(3)+f(4000000000)-32; }'
+assert 3 'int main() { char c=2; switch (c) { case sizeof(short): return 3; } return 0; }'
+assert 2 'int main() { return 1 ? 2 : 3; }'
+assert 3 'int main() { return 0 ? 2 : 3; }'
+assert 4 'int main() { return 0 ? 2 : 0 ? 3 : 4; }'
+assert 3 'int main() { return 0 ? 2 : 1 ? 3 : 4; }'
+assert 2 'int main() { return 1 ? 1 ? 2 : 3 : 4; }'
+assert 5 'int main() { int x=0; return x ? 1 : x+5; }'
+assert 7 'int main() { int x; x = 1 < 2 ? 7 : 8; return x; }'
+assert 10 'int main() { int a=0, b=0; 1 ? (a=10) : (b=20); return a+b; }'
+assert 20 'int main() { int a=0, b=0; 0 ? (a=10) : (b=20); return a+b; }'
+assert 3 'int main() { int x=1, y=2, *p = x ? &y : 0; return *p+1; }'
+assert 8 'int main() { long x=0; return sizeof(x ? 1 : x); }'
+assert 4 'int main() { char c=2; return sizeof(1 ? 1 : c); }'
+assert 3 'int main() { return (1, 2, 3); }'
+assert 5 'int main() { int i=2, j=3; return (i=5, j), i; }'
+assert 9 'int main() { int i, j; for (i=0, j=0; i<3; i++, j+=2) ; return i+j; }'
+assert 6 'int main() { int i=1; i++, i++, i*=2; return i; }'
+assert 3 'int add2(int a, int b) { return a+b; } int main() { int x=1; return add2((x++, x), 1); }'
+assert 4 'int g = 1 ? 4 : 5; int main() { return g; }'
+assert 2 'int main() { switch (2) { case 1 ? 2 : 3: return 2; } return 0; }'
+assert 7 'int f() { goto x; x: return 3; } int main() { goto x; return 1; x: return f()+4; }'
+assert 4 'struct s {char c; int i;} g; int *p=&g.i; int main() { *p=4; return g.i; }'
+assert 16 'struct s {char c; long l;} g[2]; int main() { return sizeof(g)/2; }'
+assert 0 'struct e {} a[3]; int main() { return &a[2] - &a[0]; }'
+assert 1 'struct T *p; int main() { return p == 0; }'
+assert 44 'char c = 300; int main() { return c; }'
+assert 1 'short s = 70000; int main() { return s == 4464; }'
+assert 1 'char a[2] = {255, 256}; int main() { return a[0] == -1 && a[1] == 0; }'
+assert 1 'unsigned char u = -1; int main() { return u == 255; }'
+
+assert_error "<stdin>:1:23: error: expected ';' but got '}'" 'int main() { return 1 }'
+assert_error "<stdin>:1:21: error: expected an expression but got ';'" 'int main() { return ; }'
+assert_error "<stdin>:2:1: error: expected '}' but got end of file" 'int main() { return 1; '
+assert_error "<stdin>:1:21: error: unexpected character '\$'" 'int main() { return $; }'
+assert_error "<stdin>:1:21: error: unterminated string" 'int main() { return "abc; }'
+assert_error "<stdin>:1:21: error: undeclared identifier 'x'" 'int main() { return x; }'
+assert_error "<stdin>:1:34: error: no member named 'b'" 'int main() { struct {int a;} x; x.b; }'
+assert_error "<stdin>:1:31: error: invalid application of 'sizeof' to an incomplete type 'struct T'" 'struct T; int main() { return sizeof(struct T); }'
+assert_error "<stdin>:1:34: error: invalid application of 'sizeof' to an incomplete type 'struct T'" 'struct T *p; int main() { return sizeof(*p); }'
+assert_error "<stdin>:1:40: error: arithmetic on a pointer to an incomplete type 'struct T'" 'struct T *p, *q; int main() { return p - q; }'
+assert_error "<stdin>:1:38: error: arithmetic on a pointer to an incomplete type 'struct T'" 'struct T *p; int main() { return *(p + 1) == 0; }'
+assert_error "<stdin>:1:28: error: arithmetic on a pointer to an incomplete type 'struct T'" 'struct T *p; int main() { p++; return 0; }'
+assert_error "<stdin>:1:29: error: arithmetic on a pointer to an incomplete type 'struct T'" 'struct T *p; int main() { p += 1; return 0; }'
+assert_error "<stdin>:1:37: error: passing struct s by value is not supported" 'struct s { int a; }; int f(struct s x) { return 0; } int main() { return 0; }'
+assert_error "<stdin>:1:33: error: returning struct s by value is not supported" 'struct s { int a; } x; struct s f() { return x; } int main() { return 0; }'
+assert_error "<stdin>:1:44: error: passing struct s by value is not supported" 'struct s { int a; } x; int main() { return g(x); }'
+assert_error "<stdin>:1:33: error: returning struct s by value is not supported" 'struct s { int b; } x; struct s f() { return x; } int main() { return f().b; }'
+assert_error "<stdin>:1:58: error: member access on a struct s rvalue is not supported" 'struct s { int a; } x, y; int main() { return (1 ? x : y).a; }'
+assert_error "<stdin>:1:7: error: expected a type name but got 'x'" 'int f(x) { return 0; }'
+assert_error "<stdin>:1:25: error: redefinition of 'a'" 'int main() { int a; int a; return 0; }'
+assert_error "<stdin>:1:18: error: redefinition of 'a'" 'int f(int a, int a) { return a; }'
+assert_error "<stdin>:1:7: error: array is too large" 'int a[1000000000]; int main() { return 0; }'
+assert_error "<stdin>:1:21: error: array is too large" 'int main() { char a[4000000000]; return 0; }'
+assert_error "<stdin>:1:23: error: array is too large" 'int main() { int a[2][1073741824]; return 0; }'
+assert_error "<stdin>:1:20: error: invalid array size" 'int main() { int a[99999999999999999999]; return 0; }'
+assert_error "<stdin>:1:20: error: invalid array size" 'int main() { int a[1.5]; return 0; }'
+assert_error "<stdin>:1:1: error: struct s is too large" 'struct s { char a[2000000000]; char b[2000000000]; } x; int main() { return 0; }'
+assert_error "<stdin>:1:5: error: stack frame of 'main' is too large" 'int main() { char a[2000000000]; char b[2000000000]; return 0; }'
+
+assert 3 '#define THREE 3
+int main() { return THREE; }'
+assert 5 '#define ADD 2 + 3
+int main() { return ADD; }'
+assert 4 '#define X X
+int main() { int X = 4; return X; }'
+assert 1 '#define A B
+#define B A
+int main() { int A = 1; return A; }'
+assert 2 '#define N 1
+#undef N
+int main() { int N = 2; return N; }'
+assert 6 '#define TWO 2
+#define FOUR TWO + TWO
+int main() { return FOUR + TWO; }'
+assert 8 '#define long int
+int main() { long x; return sizeof(x) + 4; }'
+assert 0 '#
+int main() { return 0; }'
+assert 7 '/* a block comment
+   over two lines */ int main() { return /* inline */ 7; }'
+assert 9 '#define NINE \
+  9
+int main() { return NINE; }'
+assert 6 '#define ADD(a, b) ((a) + (b))
+int main() { return ADD(1, ADD(2, 3)); }'
+assert 5 '#define ADD(a, b) a + b
+int main() { return ADD(ADD(1, 2), 3) * 0 + ADD((1), 1); }'
+assert 5 '#define F (x)
+int main() { int x = 5; return F; }'
+assert 4 '#define F(x) x
+int main() { int F = 4; return F; }'
+assert 8 '#define STR(x) #x
+int main() { return sizeof(STR(a  +  "b")); }'
+assert 1 '#define STR(x) #x
+int main() { return sizeof(STR()); }'
+assert 12 '#define CAT(a, b) a##b
+int main() { int CAT(x, 1) = CAT(1, 2); return x1; }'
+assert 3 '#define CAT(a, b) a ## b
+#define ONE 1
+int main() { return CAT(ON, E) + CAT(, 2); }'
+assert 8 '#define L lo ## ng
+int main() { L x; return sizeof(x); }'
+assert 11 '#define SUM(...) add(__VA_ARGS__)
+int main() { return SUM(5, 6); }'
+assert 9 '#define CALL(f, ...) f(__VA_ARGS__)
+int main() { return CALL(add, 4, 5); }'
+assert 5 '#define CALL(f, ...) f(2, ## __VA_ARGS__)
+int id(int x) { return x; }
+int main() { return CALL(id) + CALL(add, 1); }'
+assert 4 '#define f(x) x + 1
+#define g f
+int main() { int x = 3; return g(x); }'
+assert 2 '#define f(x) (x + f)
+int main() { int f = 1; return f(1); }'
+assert 7 '#define f(a) a*g
+#define g(a) f(a)
+int main() { int g = 7; return f(1)(1) / 1; }'
+assert_error "<stdin>:2:26: error: macro 'ADD' requires 2 arguments, but only 1 given" '#define ADD(a, b) a + b
+int main() { return ADD(1); }'
+assert_error "<stdin>:2:28: error: macro 'ONE' passed 2 arguments, but takes just 0" '#define ONE() 1
+int main() { return ONE(1, ); }'
+assert_error "<stdin>:2:21: error: unterminated argument list invoking macro 'F'" '#define F(x) x
+int main() { return F(1; }'
+assert_error "<stdin>:1:14: error: duplicate macro parameter 'a'" '#define F(a, a) a'
+assert_error "<stdin>:1:11: error: missing ')' in macro parameter list" '#define F(a'
+assert_error "<stdin>:1:14: error: '#' is not followed by a macro parameter" '#define F(a) #b
+int main() { return F(1); }'
+assert_error "<stdin>:2:23: error: pasting \"+\" and \"-\" does not give a valid preprocessing token" '#define F(a) a ## -
+int main() { return F(+) 1; }'
+assert_error "<stdin>:2:21: note: in expansion of macro 'BAD'" '#define BAD(x) (x + )
+int main() { return BAD(1); }'
+assert_error "<stdin>:3:21: note: in expansion of macro 'OUTER'" '#define BAD(x) (x + )
+#define OUTER BAD(1)
+int main() { return OUTER; }'
+assert 3 '#if 1
+int main() { return 3; }
+#else
+int main() { return 4; }
+#endif'
+assert 5 '#if 0
+#if 1
+#error not even read
+#endif
+int main() { return 3; }
+#elif 2 - 1 - 1
+int main() { return 4; }
+#elif 1
+int main() { return 5; }
+#else
+int main() { return 6; }
+#endif'
+assert 2 '#define N 1
+#ifdef N
+#define M 2
+#endif
+#ifndef N
+#define M 3
+#endif
+int main() { return M; }'
+assert 1 '#define A 3
+#if defined(A) && defined A && !defined(B) && (A * 2 % 4 == 2 || 1 / 0)
+int main() { return 1; }
+#endif'
+assert 7 '#if 10 - 2 + 1 == 9 && -1 < 0 && UNDEFINED == 0
+int main() { return 7; }
+#endif'
+assert 5 '#if 0x10 == 16 && 010 == 8
+int main() { return 5; }
+#endif'
+assert 6 '#if (1 << 4 | 3) == 19 && (6 & 3 ^ 1) == 3 && ~0 == -1 && 64 >> 2 % 3 == 16
+int main() { return 6; }
+#endif'
+assert 4 '#if 0 ? 1 / 0 : 1 ? (2 ? 4 : 5) : 6
+int main() { return 4; }
+#endif'
+assert 9 '#define F(x) (x + 1)
+#if F(2) >= 3 && F(2) <= 3 && F(2) != 4 && F(2) > 2
+int main() { return 9; }
+#endif'
+assert 3 '#define HERE __LINE__
+
+int main() { return HERE; }'
+assert 8 'int main() { return sizeof(__FILE__); }'
+assert 20 'int main() { return __COUNTER__ + __COUNTER__ * 10 + __COUNTER__ * 5; }'
+assert 1 '#if __STDC__ && __STDC_VERSION__ >= 201112L && defined(__x86_64__) && __LP64__ && __linux__
+int main() { return __CHAR_BIT__ / 8; }
+#endif'
+assert 12 'int main() { return sizeof(__DATE__) + sizeof(__TIME__) - 9; }'
+assert 9 '#line 7
+int main() { return __LINE__ + 2; }'
+assert_error "foo.c:20:21: error: undeclared identifier 'x'" '# 20 "foo.c"
+int main() { return x; }'
+assert_error "<stdin>:1:7: error: #line directive requires a positive integer argument" '#line 0'
+assert_error "<stdin>:1:2: error: unterminated conditional directive" '#if 1'
+assert_error "<stdin>:1:2: error: #endif without #if" '#endif'
+assert_error "<stdin>:3:2: error: #elif after #else" '#if 1
+#else
+#elif 1
+#endif'
+assert_error "<stdin>:1:2: error: #if with no expression" '#if
+#endif'
+assert_error "<stdin>:1:7: error: missing binary operator before '2'" '#if 1 2
+#endif'
+assert_error "<stdin>:1:7: error: division by zero in preprocessor expression" '#if 1 / 0
+#endif'
+assert_error "<stdin>:1:13: error: missing ')' after 'defined'" '#if defined(A
+#endif'
+assert_error "<stdin>:1:2: error: invalid preprocessing directive #foo" '#foo'
+assert_error "<stdin>:1:29: error: invalid operand to unary ~ (int *)" 'int main() { int *p; return ~p; }'
+assert_error "<stdin>:1:31: error: invalid operands to binary % (int * and int)" 'int main() { int *p; return p % 2; }'
+assert_error "<stdin>:1:26: error: expected ':' but got ';'" 'int main() { return 1 ? 2; }'
+assert_error "<stdin>:1:42: error: incompatible operand types (struct (anonymous) and int)" 'int main() { struct {int a;} s; return 1 ? s : 1; }'
+assert_error "<stdin>:1:44: error: operand of type struct s where arithmetic or pointer type is required" 'struct s { int a; } x; int main() { return (int)x; }'
+assert_error "<stdin>:1:28: error: used type struct s where arithmetic or pointer type is required" 'int main() { int x; return (struct s { int a; })x; }'
+assert_error "<stdin>:1:28: error: invalid combination of type specifiers" 'int main() { unsigned char short x; return 0; }'
+assert_error "<stdin>:1:21: error: invalid suffix 'x' on integer constant" 'int main() { return 1x; }'
+assert_error "<stdin>:1:21: error: invalid suffix 'lL' on integer constant" 'int main() { return 1lL; }'
+assert_error "<stdin>:1:21: error: invalid suffix '.5' on integer constant" 'int main() { return 1.5; }'
+assert_error "<stdin>:1:21: error: invalid digit '9' in octal constant" 'int main() { return 09; }'
+assert_error "<stdin>:1:21: error: invalid suffix 'x' on integer constant" 'int main() { return 0x; }'
+assert_error "<stdin>:1:21: error: integer constant is too large for any integer type" 'int main() { return 18446744073709551616; }'
+assert_error "<stdin>:1:21: error: invalid combination of type specifiers" 'int main() { signed unsigned x; return 0; }'
+assert_error "<stdin>:1:24: error: invalid combination of type specifiers" 'int main() { long long long x; return 0; }'
+assert_error "<stdin>:1:9: error: expected ':' in preprocessor expression" '#if 1 ? 2
+#endif'
+assert_error "<stdin>:1:14: error: 'case' statement not in switch statement" 'int main() { case 1: return 0; }'
+assert_error "<stdin>:1:36: error: multiple default labels in one switch" 'int main() { switch (1) { default: default: ; } }'
+assert_error "<stdin>:1:37: error: duplicate case value '1'" 'int main() { switch (1) { case 1: ; case 2-1: ; } }'
+assert_error "<stdin>:1:34: error: expression is not an integer constant expression" 'int main() { int x; switch (1) { case x: ; } }'
+assert_error "<stdin>:1:22: error: statement requires expression of integer type (int * invalid)" 'int main() { int *p; switch (p) { } }'
+assert_error "<stdin>:1:21: error: expected 'while' but got ';'" 'int main() { do { } ; return 0; }'
+assert_error "<stdin>:1:29: error: expected ';' but got '}'" 'int main() { do ; while (0) }'
+assert_error "<stdin>:1:14: error: 'break' statement not in loop or switch statement" 'int main() { break; }'
+assert_error "<stdin>:1:23: error: 'continue' statement not in loop statement" 'int main() { if (1) { continue; } }'
+assert_error "<stdin>:1:19: error: use of undeclared label 'nowhere'" 'int main() { goto nowhere; }'
+assert_error "<stdin>:1:19: error: redefinition of label 'a'" 'int main() { a: ; a: return 0; }'
+assert_error "<stdin>:1:16: error: expression is not assignable" 'int main() { 1 += 2; }'
+assert_error "<stdin>:1:24: error: expression is not assignable" 'int main() { int x; x++++; }'
+assert_error "<stdin>:1:33: error: invalid operand to unary ++ (struct (anonymous))" 'int main() { struct {int a;} s; ++s; }'
+assert_error "<stdin>:1:31: error: invalid operands to binary *= (int * and int)" 'int main() { int *p; return p *= 2; }'
+assert_error "<stdin>:1:34: error: invalid operands to binary += (int and int *)" 'int main() { int *p, i; return i += p; }'
+assert_error "<stdin>:1:10: error: 'nope.h' file not found" '#include "nope.h"'
+assert_error "<stdin>:1:10: error: #include expects \"FILENAME\" or <FILENAME>" '#include 3'
+# only the -I directories are searched, not the system headers
+assert_error "<stdin>:1:10: error: 'stdio.h' file not found" '#include <stdio.h>'
+assert_error "<stdin>:1:9: error: macro name must be an identifier" '#define 1 2'
+assert_error "<stdin>:1:14: error: unterminated comment" 'int main() { /* no end'
+
+# included files are searched next to the includer and in -I paths,
+# diagnostics point into the header
+mkdir -p tmp-inc/sys
+echo '#include "tmp-inner.h"' > tmp-inc/sys/tmp-outer.h
+echo '#define INNER 11' > tmp-inc/sys/tmp-inner.h
+echo '#define ANGLED 20' > tmp-inc/tmp-angled.h
+echo 'int broken() { return x; }' > tmp-inc/tmp-broken.h
+cat <<'EOF' > tmp-include.c
+#include "tmp-inc/sys/tmp-outer.h"
+#include <tmp-angled.h>
+int main() { return INNER + ANGLED; }
+EOF
+./chibicc -I tmp-inc -o tmp.s tmp-include.c || exit
+gcc -static -m64 -o tmp tmp.s
+./tmp
+[ "$?" = 31 ] || { echo "tmp-include.c => 31 expected"; exit 1; }
+echo "tmp-include.c => 31"
+
+echo '#include <tmp-broken.h>' | ./chibicc -Itmp-inc -o tmp.s - 2>&1 |
+  grep -q "^tmp-inc/tmp-broken.h:1:23: error: undeclared identifier 'x'" ||
+  { echo "tmp-broken.h => error in the header expected"; exit 1; }
+echo "tmp-broken.h => tmp-inc/tmp-broken.h:1:23: error"
+
+# include guards and macros given on the command line
+cat <<'EOF' > tmp-inc/tmp-guarded.h
+#ifndef TMP_GUARDED_H
+#define TMP_GUARDED_H
+struct guarded { int x; };
+#endif
+EOF
+cat <<'EOF' > tmp-guard.c
+#include "tmp-inc/tmp-guarded.h"
+#include "tmp-inc/tmp-guarded.h"
+#ifdef OFF
+int main() { return 1; }
+#else
+int main() { struct guarded g; g.x = N + ONE; return g.x; }
+#endif
+EOF
+./chibicc -DN=3 -D ONE -DOFF -UOFF -o tmp.s tmp-guard.c || exit
+gcc -static -m64 -o tmp tmp.s
+./tmp
+[ "$?" = 4 ] || { echo "tmp-guard.c => 4 expected"; exit 1; }
+echo "tmp-guard.c -DN=3 -D ONE -DOFF -UOFF => 4"
+
+echo '#if 1' > tmp-inc/tmp-open.h
+echo '#include "tmp-inc/tmp-open.h"
+#endif' | ./chibicc -o tmp.s - 2>&1 |
+  grep -q "^tmp-inc/tmp-open.h:1:2: error: unterminated conditional directive" ||
+  { echo "tmp-open.h => unterminated #if in the header expected"; exit 1; }
+echo "tmp-open.h => tmp-inc/tmp-open.h:1:2: error"
+
+# -E prints the preprocessed source with linemarkers, which compiles to the
+# same program
+echo '#define NEG -' > tmp-inc/tmp-neg.h
+cat <<'EOF' > tmp-pp.c
+#include "tmp-inc/tmp-neg.h"
+#define SUM(a, b) a + \
+  b
+int main() {
+  int x = -NEG 1;
+
+
+
+  return SUM(x,  2)   +  __LINE__;
+}
+EOF
+cat <<'EOF' > tmp-pp.expected
+# 4 "tmp-pp.c"
+int main() {
+  int x = - - 1;
+
+
+
+  return x + 2 + 9;
+}
+EOF
+./chibicc -E tmp-pp.c -o tmp-pp.i || exit
+diff tmp-pp.expected tmp-pp.i || { echo "tmp-pp.c -E => unexpected output"; exit 1; }
+./chibicc -o tmp.s tmp-pp.i || exit
+gcc -static -m64 -o tmp tmp.s
+./tmp
+[ "$?" = 12 ] || { echo "tmp-pp.i => 12 expected"; exit 1; }
+echo "tmp-pp.c -E => tmp-pp.i => 12"
+
+# -dM -E prints the macros defined at the end, __DATE__ and __TIME__ come
+# from SOURCE_DATE_EPOCH when it is set
+macros=$(echo '#define F(a, ...) a + __VA_ARGS__
+#undef __unix' | SOURCE_DATE_EPOCH=1700000000 ./chibicc -dM -E -)
+for def in '#define F(a,...) a + __VA_ARGS__' '#define __STDC__ 1' \
+  '#define __STDC_VERSION__ 201112L' '#define __DATE__ "Nov 14 2023"' '#define __TIME__ "22:13:20"'; do
+  echo "$macros" | grep -qxF "$def" || { echo "-dM -E => '$def' expected"; exit 1; }
+done
+echo "$macros" | grep -q "__unix \|__LINE__" && { echo "-dM -E => no __unix or __LINE__ expected"; exit 1; }
+echo "-dM -E => $(echo "$macros" | wc -l) macros"
+
+# each file given on the command line is compiled to its own .s
+echo 'int ret7() { return 7; }' > tmp-lib.c
+echo 'int main() { return ret7(); }' > tmp-main.c
+./chibicc tmp-lib.c tmp-main.c || exit
+gcc -static -m64 -o tmp tmp-lib.s tmp-main.s
+./tmp
+[ "$?" = 7 ] || { echo "tmp-lib.c tmp-main.c => 7 expected"; exit 1; }
+echo "tmp-lib.c tmp-main.c => 7"
+
+# dense case values jump through a table, sparse ones are compared in turn
+echo 'int f(int x) { switch (x) { case 1: case 2: case 3: case 5: return 1; } return 0; }' |
+  ./chibicc -o tmp.s - && grep -q '^\.L\.table\.' tmp.s ||
+  { echo "dense switch => jump table expected"; exit 1; }
+echo 'int f(int x) { switch (x) { case 1: case 10: case 100: case 1000: return 1; } return 0; }' |
+  ./chibicc -o tmp.s - && ! grep -q '\.L\.table\.' tmp.s ||
+  { echo "sparse switch => no jump table expected"; exit 1; }
+echo "switch lowering => ok"
+
+# diagnostics name the file they come from
+echo 'int main() { return x; }' > tmp-err.c
+./chibicc -o tmp.s tmp-err.c 2>&1 | grep -q "^tmp-err.c:1:21: error" ||
+  { echo "tmp-err.c => no diagnostic for tmp-err.c:1:21"; exit 1; }
+echo "tmp-err.c => tmp-err.c:1:21: error"
+
+# errors quote the source line with a marker under the offending token
+echo 'int main() { return foo + 1; }' | ./chibicc -fno-diagnostics-color -o tmp.s - 2> tmp-err.txt
+cat <<'EOF' | diff - tmp-err.txt || { echo "caret snippet differs"; exit 1; }
+<stdin>:1:21: error: undeclared identifier 'foo'
+int main() { return foo + 1; }
+                    ^~~
+EOF
+echo "caret snippet => ok"
+
+echo 'int main() { return x; }' | ./chibicc -fdiagnostics-color=always -o tmp.s - 2>&1 |
+  grep -q $'\x1b\[1;31merror:' || { echo "colored diagnostics => escapes expected"; exit 1; }
+echo "colored diagnostics => ok"
+
+# the parser recovers after a syntax error and reports the following ones
+cat <<'EOF' > tmp-errs.c
+int f(int a) {
+  int x = 1
+  return x + ;
+}
+struct s { int a; int b };
+int g();
+int main() {
+  if (a > ) { a = 2; }
+  int a[3] = {0};
+  return f(1) +;
+}
+EOF
+errors=$(./chibicc -o tmp.s tmp-errs.c 2>&1 | grep -c ': error: ')
+[ "$errors" = 7 ] || { echo "tmp-errs.c => 7 errors expected, but got $errors"; exit 1; }
+echo "tmp-errs.c => 7 errors"
+
+errors=$(./chibicc -fmax-errors=2 -o tmp.s tmp-errs.c 2>&1)
+[ "$(echo "$errors" | grep -c ': error: ')" = 2 ] &&
+  echo "$errors" | grep -q "compilation terminated due to -fmax-errors=2." ||
+  { echo "-fmax-errors=2 => 2 errors expected, but got '$errors'"; exit 1; }
+echo "-fmax-errors=2 => 2 errors"
 
 echo OK